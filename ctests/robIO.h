@@ -5,6 +5,9 @@
 #include <string.h>
 #include <stdarg.h>
 #include "vec.h"
+#include "robtext.h"
+#include "robvec.h"
+#include "robarray.h"
 
 
 
@@ -0,0 +1,61 @@
+#ifndef ROBARRAY_H
+#define ROBARRAY_H
+
+#include <stdbool.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+/*
+ * A fixed size array in roblang is a plain C array of Num or Bool. It is passed around as a
+ * pointer to its first element, while its length is known to the compiler. Indices into arrays
+ * and Vecs that cannot be checked at compile time go through rob_index, unless the program was
+ * built with --no-bounds-checks.
+ */
+
+/* The roblang file the program was compiled from, named when an index is out of bounds. */
+#ifndef ROB_FILE
+#define ROB_FILE "unknown"
+#endif
+
+/**
+ * @brief Aborts the program if index is not within 0..len, otherwise evaluates to it.
+ * @param line The line of the roblang source the index appears on.
+ */
+static inline int rob_bounds_check(int index, int len, const char* file, int line) {
+    if (index < 0 || index >= len) {
+	fprintf(stderr, "%s:%d: Index %d is out of bounds for length %d\n", file, line, index, len);
+	exit(EXIT_FAILURE);
+    }
+    return index;
+}
+
+#ifdef ROB_NO_BOUNDS_CHECKS
+#define rob_index(index, len, line) (index)
+#else
+#define rob_index(index, len, line) rob_bounds_check(index, len, ROB_FILE, line)
+#endif
+
+/**
+ * @brief Sets every element of an array of Num to value and returns the array.
+ */
+static inline int* rob_array_fill_int(int* array, int len, int value) {
+    for (int i = 0; i < len; i++) {
+	array[i] = value;
+    }
+    return array;
+}
+
+/**
+ * @brief Sets every element of an array of Bool to value and returns the array.
+ */
+static inline bool* rob_array_fill_bool(bool* array, int len, bool value) {
+    for (int i = 0; i < len; i++) {
+	array[i] = value;
+    }
+    return array;
+}
+
+/* Number of elements in the array, which the compiler passes along as len. */
+#define rob_array_len(array, len) (len)
+
+#endif
@@ -0,0 +1,163 @@
+#ifndef ROBTEXT_H
+#define ROBTEXT_H
+
+#include <stdbool.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+/*
+ * Text in roblang is a NUL terminated char*. Every Text variable owns a heap copy of its
+ * characters, which is released when the variable goes out of scope. Text created while an
+ * expression is evaluated (concatenations, slices and the results of calls) is owned by the
+ * rob_scope of the enclosing block instead, and released together with it.
+ */
+
+/**
+ * @brief A temporary along with the function that releases it.
+ */
+typedef struct rob_owned {
+    void* value;
+    void (*drop)(void*);
+} rob_owned;
+
+/**
+ * @brief Temporaries created in one block, released when the block is left.
+ */
+typedef struct rob_scope {
+    rob_owned* owned;
+    int len;
+    int cap;
+} rob_scope;
+
+/**
+ * @brief Releases every temporary owned by the scope. Called by the cleanup attribute of ROB_SCOPE.
+ */
+static inline void rob_scope_drop(rob_scope* scope) {
+    for (int i = 0; i < scope->len; i++) {
+	scope->owned[i].drop(scope->owned[i].value);
+    }
+    free(scope->owned);
+}
+
+/**
+ * @brief Releases the Text owned by a variable. Called by the cleanup attribute of ROB_OWNED.
+ */
+static inline void rob_text_drop(char** text) {
+    free(*text);
+}
+
+/* Declares the scope owning the temporaries of the current block, named _rs so inner blocks shadow it. */
+#define ROB_SCOPE rob_scope _rs __attribute__((cleanup(rob_scope_drop))) = {0}
+
+/* Marks a char* variable as owning its Text. */
+#define ROB_OWNED __attribute__((cleanup(rob_text_drop)))
+
+static inline void* rob_text_alloc(size_t size) {
+    void* memory = malloc(size);
+    if (memory == NULL) {
+	fprintf(stderr, "Out of memory\n");
+	exit(EXIT_FAILURE);
+    }
+    return memory;
+}
+
+/**
+ * @brief Copies Text onto the heap. The caller owns the copy.
+ */
+static inline char* rob_text_clone(const char* text) {
+    size_t len = strlen(text);
+    char* copy = rob_text_alloc(len + 1);
+    memcpy(copy, text, len + 1);
+    return copy;
+}
+
+/**
+ * @brief Hands a temporary to a scope, which releases it with drop when the block is left.
+ */
+static inline void* rob_scope_adopt(rob_scope* scope, void* value, void (*drop)(void*)) {
+    if (scope->len == scope->cap) {
+	int cap = scope->cap == 0 ? 8 : scope->cap * 2;
+	rob_owned* owned = realloc(scope->owned, cap * sizeof(rob_owned));
+	if (owned == NULL) {
+	    fprintf(stderr, "Out of memory\n");
+	    exit(EXIT_FAILURE);
+	}
+	scope->owned = owned;
+	scope->cap = cap;
+    }
+    scope->owned[scope->len++] = (rob_owned){value, drop};
+    return value;
+}
+
+/**
+ * @brief Hands heap allocated Text to a scope, which releases it when the block is left.
+ */
+static inline char* rob_text_adopt(rob_scope* scope, char* text) {
+    return rob_scope_adopt(scope, text, free);
+}
+
+/**
+ * @brief Replaces the Text owned by a variable with a copy of new_text.
+ * new_text is copied before the old Text is released, as it may be the same Text.
+ */
+static inline void rob_text_set(char** slot, const char* new_text) {
+    char* copy = rob_text_clone(new_text);
+    free(*slot);
+    *slot = copy;
+}
+
+/**
+ * @brief Joins two Texts into newly allocated Text.
+ */
+static inline char* rob_text_concat(const char* left, const char* right) {
+    size_t left_len = strlen(left);
+    size_t right_len = strlen(right);
+    char* joined = rob_text_alloc(left_len + right_len + 1);
+    memcpy(joined, left, left_len);
+    memcpy(joined + left_len, right, right_len + 1);
+    return joined;
+}
+
+/**
+ * @brief Compares two Texts by content.
+ */
+static inline bool rob_text_eq(const char* left, const char* right) {
+    return strcmp(left, right) == 0;
+}
+
+/**
+ * @brief Number of characters in the Text.
+ */
+static inline int rob_text_len(const char* text) {
+    return (int)strlen(text);
+}
+
+/**
+ * @brief The characters from start up to, but not including, end as newly allocated Text.
+ * Aborts if the range does not lie within the Text.
+ */
+static inline char* rob_text_slice(const char* text, int start, int end) {
+    int len = rob_text_len(text);
+    if (start < 0 || end < start || end > len) {
+	fprintf(stderr, "Slice %d..%d is out of range for Text of length %d\n", start, end, len);
+	exit(EXIT_FAILURE);
+    }
+    char* slice = rob_text_alloc(end - start + 1);
+    memcpy(slice, text + start, end - start);
+    slice[end - start] = '\0';
+    return slice;
+}
+
+/**
+ * @brief Index of the first occurrence of needle in the Text, or -1 if it does not occur.
+ */
+static inline int rob_text_find(const char* text, const char* needle) {
+    const char* found = strstr(text, needle);
+    if (found == NULL) {
+	return -1;
+    }
+    return (int)(found - text);
+}
+
+#endif
@@ -0,0 +1,173 @@
+#ifndef ROBVEC_H
+#define ROBVEC_H
+
+#include "robtext.h"
+#include "vec.h"
+
+/*
+ * A Vec in roblang is a c-vector, a T* to its first element with the length stored in front of
+ * it. Like Text, every Vec variable owns its own copy, released when the variable goes out of
+ * scope, and Vecs created while an expression is evaluated are owned by the rob_scope of the
+ * enclosing block. A Vec[Text] also owns a copy of each of its elements, so it has its own set
+ * of rob_vec_text helpers.
+ */
+
+/**
+ * @brief Releases a Vec. Does nothing for a variable that was declared but never assigned.
+ */
+static inline void rob_vec_free(void* vec) {
+    if (vec != NULL) {
+	vector_free(vec);
+    }
+}
+
+/**
+ * @brief Releases a Vec[Text] along with every Text in it.
+ */
+static inline void rob_vec_text_free(void* vec) {
+    if (vec == NULL) {
+	return;
+    }
+    char** texts = vec;
+    for (vec_size_t i = 0; i < vector_size(vec); i++) {
+	free(texts[i]);
+    }
+    vector_free(vec);
+}
+
+/**
+ * @brief Called by the cleanup attribute of ROB_OWNED_VEC.
+ */
+static inline void rob_vec_drop(void* vec_addr) {
+    rob_vec_free(*(void**)vec_addr);
+}
+
+/**
+ * @brief Called by the cleanup attribute of ROB_OWNED_TEXT_VEC.
+ */
+static inline void rob_vec_text_drop(void* vec_addr) {
+    rob_vec_text_free(*(void**)vec_addr);
+}
+
+/* Marks a variable as owning its Vec. */
+#define ROB_OWNED_VEC __attribute__((cleanup(rob_vec_drop)))
+
+/* Marks a variable as owning its Vec[Text] and the Text in it. */
+#define ROB_OWNED_TEXT_VEC __attribute__((cleanup(rob_vec_text_drop)))
+
+/* Hands a Vec to a scope, which releases it when the block is left. */
+#define rob_vec_adopt(scope, vec) ((typeof(vec))rob_scope_adopt(scope, vec, rob_vec_free))
+#define rob_vec_text_adopt(scope, vec) ((typeof(vec))rob_scope_adopt(scope, vec, rob_vec_text_free))
+
+/**
+ * @brief Copies a Vec with elements of type_size bytes onto the heap. The caller owns the copy.
+ * vector_copy is not used, as its copy claims the capacity of the original without having it.
+ */
+static inline void* rob_vec_copy_bytes(void* vec, vec_type_t type_size) {
+    vector copy = vector_create();
+    for (vec_size_t i = 0; i < vector_size(vec); i++) {
+	memcpy(_vector_add(&copy, type_size), (char*)vec + i * type_size, type_size);
+    }
+    return copy;
+}
+
+#define rob_vec_copy(vec) ((typeof(vec))rob_vec_copy_bytes(vec, sizeof(*(vec))))
+
+/**
+ * @brief Copies a Vec[Text] along with every Text in it. The caller owns the copy.
+ */
+static inline char** rob_vec_text_copy(char** vec) {
+    char** copy = rob_vec_copy(vec);
+    for (vec_size_t i = 0; i < vector_size(copy); i++) {
+	copy[i] = rob_text_clone(copy[i]);
+    }
+    return copy;
+}
+
+/*
+ * Replaces the Vec owned by a variable with a copy of vec. vec is copied before the old Vec is
+ * released, as it may be the same Vec.
+ */
+#define rob_vec_set(slot, vec)                                                                     \
+    ({                                                                                             \
+	typeof(*(slot)) _copy = rob_vec_copy(vec);                                                 \
+	rob_vec_free(*(slot));                                                                     \
+	*(slot) = _copy;                                                                           \
+    })
+
+static inline void rob_vec_text_set(char*** slot, char** vec) {
+    char** copy = rob_vec_text_copy(vec);
+    rob_vec_text_free(*slot);
+    *slot = copy;
+}
+
+/* Number of elements in the Vec. */
+#define rob_vec_len(vec) ((int)vector_size(vec))
+
+/*
+ * Appends value to the Vec, which may move it. value is evaluated before, as it may read the Vec.
+ */
+#define rob_vec_push(vec, value)                                                                   \
+    ({                                                                                             \
+	typeof(*(vec)) _value = (value);                                                           \
+	vector_add(&(vec), _value);                                                                \
+    })
+
+/* Appends a copy of the Text to a Vec[Text]. */
+#define rob_vec_text_push(vec, value) rob_vec_push(vec, rob_text_clone(value))
+
+/**
+ * @brief Aborts when popping from an empty Vec.
+ */
+static inline void rob_vec_check_pop(void* vec) {
+    if (vector_size(vec) == 0) {
+	fprintf(stderr, "Cannot pop from an empty Vec\n");
+	exit(EXIT_FAILURE);
+    }
+}
+
+/* Removes the last element of the Vec and evaluates to it. Popped Text is owned by the caller. */
+#define rob_vec_pop(vec) (rob_vec_check_pop(vec), vector_pop(vec), (vec)[vector_size(vec)])
+
+/*
+ * Calls the function value f on every element of the Vec, collecting the results in a new Vec of
+ * out owned by the caller. call is the call helper of the type of f. Text returned by f is
+ * already owned by the caller, so it is stored as it is.
+ */
+#define rob_vec_map(vec, f, call, out)                                                             \
+    ({                                                                                             \
+	typeof(vec) _src = (vec);                                                                  \
+	typeof(f) _f = (f);                                                                        \
+	out* _out = vector_create();                                                               \
+	for (int _i = 0; _i < rob_vec_len(_src); _i++) {                                           \
+	    out _mapped = call(_f, _src[_i]);                                                      \
+	    vector_add(&_out, _mapped);                                                            \
+	}                                                                                          \
+	_out;                                                                                      \
+    })
+
+/*
+ * Collects copies of the elements of the Vec the function value f returns true for in a new Vec
+ * owned by the caller. call is the call helper of the type of f.
+ */
+#define rob_vec_filter_copying(vec, f, call, copy)                                                 \
+    ({                                                                                             \
+	typeof(vec) _src = (vec);                                                                  \
+	typeof(f) _f = (f);                                                                        \
+	typeof(vec) _out = vector_create();                                                        \
+	for (int _i = 0; _i < rob_vec_len(_src); _i++) {                                           \
+	    if (call(_f, _src[_i])) {                                                              \
+		typeof(*_src) _kept = copy(_src[_i]);                                              \
+		vector_add(&_out, _kept);                                                          \
+	    }                                                                                      \
+	}                                                                                          \
+	_out;                                                                                      \
+    })
+
+/* Elements of a Vec other than a Vec[Text] own nothing and are copied as they are. */
+#define rob_vec_elem_copy(value) (value)
+
+#define rob_vec_filter(vec, f, call) rob_vec_filter_copying(vec, f, call, rob_vec_elem_copy)
+#define rob_vec_text_filter(vec, f, call) rob_vec_filter_copying(vec, f, call, rob_text_clone)
+
+#endif
@@ -0,0 +1,453 @@
+use crate::analysis::bindings::{assigned_twice, Bindings};
+use crate::diagnostics::diagnostic::{Diagnostic, ErrorCode, Span};
+use crate::parsing::nodes::*;
+
+/// Definite assignment analysis. Every read of a binding has to happen after it has been
+/// assigned on all paths leading to it, and an immutable binding may only be assigned if it has
+/// not possibly been assigned before. Functions returning a value have to return on every path.
+pub fn check(program: &Program) -> Result<(), Vec<Diagnostic>> {
+    let mut checker = Checker {
+        vars: Bindings::new(),
+        loops: Vec::new(),
+        function: String::new(),
+    };
+    let mut flow = Flow::new();
+    checker.block(&program.children, &mut flow);
+    checker.vars.finish()
+}
+
+/// What is known about a binding, which is only whether it may be assigned again.
+struct Var {
+    mutable: bool,
+}
+
+/// Assignment state of every tracked binding at one point in the program, indexed by the
+/// binding's index in `Checker::vars`.
+#[derive(Clone)]
+struct Flow {
+    definite: Vec<bool>,
+    maybe: Vec<bool>,
+    /// Set once the path has returned, after which nothing is reachable.
+    diverges: bool,
+}
+
+impl Flow {
+    fn new() -> Self {
+        Flow {
+            definite: Vec::new(),
+            maybe: Vec::new(),
+            diverges: false,
+        }
+    }
+
+    fn set(&mut self, id: usize, definite: bool, maybe: bool) {
+        if self.definite.len() <= id {
+            self.definite.resize(id + 1, false);
+            self.maybe.resize(id + 1, false);
+        }
+        self.definite[id] = definite;
+        self.maybe[id] = maybe;
+    }
+
+    fn definite(&self, id: usize) -> bool {
+        self.diverges || self.definite.get(id).copied().unwrap_or(false)
+    }
+
+    fn maybe(&self, id: usize) -> bool {
+        self.maybe.get(id).copied().unwrap_or(false)
+    }
+
+    /// State after two paths meet again.
+    fn join(&self, other: &Flow) -> Flow {
+        if self.diverges {
+            return other.clone();
+        }
+        if other.diverges {
+            return self.clone();
+        }
+        let len = self.definite.len().min(other.definite.len());
+        Flow {
+            definite: (0..len)
+                .map(|i| self.definite[i] && other.definite[i])
+                .collect(),
+            maybe: (0..len).map(|i| self.maybe[i] || other.maybe[i]).collect(),
+            diverges: false,
+        }
+    }
+}
+
+/// The paths that leave a loop body early: everything possibly assigned when going back to the
+/// start of the loop through `continue`, and the state when leaving it through `break`.
+struct LoopExits {
+    label: Option<LoopLabel>,
+    continued: Vec<bool>,
+    /// Diverges until some `break` is reached.
+    broken: Flow,
+}
+
+fn union_maybe(target: &mut Vec<bool>, other: &Vec<bool>) {
+    for (id, maybe) in target.iter_mut().enumerate() {
+        *maybe = *maybe || other.get(id).copied().unwrap_or(false);
+    }
+}
+
+struct Checker {
+    vars: Bindings<Var>,
+    loops: Vec<LoopExits>,
+    /// The function being checked, as named in errors.
+    function: String,
+}
+
+impl Checker {
+    fn declare(&mut self, ident: &IdentifierNode, assigned: bool, flow: &mut Flow) {
+        let mutable = ident.mutable;
+        let id = self.vars.declare(ident, Var { mutable });
+        flow.set(id, assigned, assigned);
+    }
+
+    fn error(&mut self, code: ErrorCode, span: &Span, message: String, id: usize) {
+        let message = format!("In {}: {}", self.function, message);
+        let diagnostic = Diagnostic::error(code, span, message);
+        self.vars.report_at(diagnostic, id);
+    }
+
+    fn read(&mut self, name: &String, span: &Span, flow: &Flow) {
+        let Some(id) = self.vars.resolve(name) else {
+            return;
+        };
+        if !flow.definite(id) {
+            self.error(
+                ErrorCode::Unassigned,
+                span,
+                format!("{name} is used before it is definitely assigned"),
+                id,
+            );
+        }
+    }
+
+    fn assign(&mut self, name: &String, span: &Span, flow: &mut Flow) {
+        let Some(id) = self.vars.resolve(name) else {
+            return;
+        };
+        if !self.vars[id].info.mutable && flow.maybe(id) && !flow.diverges {
+            let diagnostic = assigned_twice(name, span, &self.vars[id].declared);
+            self.vars.report(diagnostic);
+        }
+        flow.set(id, true, true);
+    }
+
+    fn scoped_block(&mut self, children: &Vec<StatementNode>, flow: &mut Flow) {
+        self.vars.enter();
+        self.block(children, flow);
+        self.vars.leave();
+    }
+
+    fn block(&mut self, children: &Vec<StatementNode>, flow: &mut Flow) {
+        for child in children {
+            self.statement(child, flow);
+        }
+    }
+
+    fn statement(&mut self, stmt: &StatementNode, flow: &mut Flow) {
+        match stmt {
+            StatementNode::DeclareAssign(node) => {
+                if let Some(value) = &node.ident.value {
+                    if let Value::Func(func) = value.as_ref() {
+                        self.function_body(func, flow);
+                    } else {
+                        self.value(value, flow);
+                    }
+                }
+                self.declare(&node.ident, true, flow);
+            }
+            StatementNode::Declare(node) => {
+                self.declare(&node.ident, false, flow);
+            }
+            StatementNode::Assign(node) => {
+                if let Some(value) = &node.ident.value {
+                    self.value(value, flow);
+                }
+                if let Some(index) = &node.index {
+                    // Writing an element needs the Vec to exist already
+                    self.number(index, flow);
+                    self.read(&node.ident.name, &node.span, flow);
+                } else if !node.fields.is_empty() {
+                    // The other fields keep their values, so the struct has to be assigned
+                    self.read(&node.ident.name, &node.span, flow);
+                } else if node.deref {
+                    // Writing through a pointer only needs the pointer itself
+                    self.read(&node.ident.name, &node.span, flow);
+                } else {
+                    self.assign(&node.ident.name, &node.span, flow);
+                }
+            }
+            StatementNode::Call(node) => self.call(node, flow),
+            StatementNode::Conditional(node) => {
+                self.boolean(&node.condition, flow);
+                let mut body = flow.clone();
+                self.scoped_block(&node.body.children, &mut body);
+                let mut joined = body;
+                // Each else if condition is only evaluated once the previous ones were false
+                let mut rest = flow.clone();
+                for elif in &node.elifs {
+                    self.boolean(&elif.condition, &rest);
+                    let mut elifbody = rest.clone();
+                    self.scoped_block(&elif.body.children, &mut elifbody);
+                    joined = joined.join(&elifbody);
+                }
+                if let Some(block) = &node.i_else {
+                    self.scoped_block(&block.children, &mut rest);
+                }
+                *flow = joined.join(&rest);
+            }
+            StatementNode::Return(node) => {
+                self.value(&node.value, flow);
+                flow.diverges = true;
+            }
+            StatementNode::Inline(_) => {}
+            StatementNode::ForLoop(node) => {
+                match &node.iter {
+                    Iterable::Range(range) => {
+                        self.number(&range.start, flow);
+                        self.number(&range.end, flow);
+                    }
+                    Iterable::Elements(elements) => self.value(elements, flow),
+                }
+                let body = &node.body.children;
+                self.loop_body(body, Some(&node.dec), &node.label, false, flow);
+            }
+            StatementNode::WhileLoop(node) => {
+                self.boolean(&node.condition, flow);
+                // `while True` is only left through a break
+                let endless = matches!(node.condition, Bool::Lit(BoolLiteral::True, _));
+                self.loop_body(&node.body.children, None, &node.label, endless, flow);
+            }
+            StatementNode::Break(node) => self.loop_exit(node, true, flow),
+            StatementNode::Continue(node) => self.loop_exit(node, false, flow),
+            StatementNode::Match(node) => {
+                self.value(&node.value, flow);
+                // Matches are exhaustive, so exactly one of the arms runs
+                let mut joined: Option<Flow> = None;
+                for arm in &node.arms {
+                    let mut armflow = flow.clone();
+                    self.vars.enter();
+                    for (_, binding) in &arm.bindings {
+                        self.declare(binding, true, &mut armflow);
+                    }
+                    self.block(&arm.body.children, &mut armflow);
+                    self.vars.leave();
+                    joined = Some(match joined {
+                        Some(earlier) => earlier.join(&armflow),
+                        None => armflow,
+                    });
+                }
+                if let Some(joined) = joined {
+                    *flow = joined;
+                }
+            }
+        }
+    }
+
+    fn loop_exit(&mut self, node: &LoopControl, broken: bool, flow: &mut Flow) {
+        if !flow.diverges {
+            let target = match &node.label {
+                Some(label) => self
+                    .loops
+                    .iter_mut()
+                    .rev()
+                    .find(|exit| exit.label.as_ref() == Some(label)),
+                None => self.loops.last_mut(),
+            };
+            if let Some(target) = target {
+                if broken {
+                    target.broken = target.broken.join(flow);
+                } else {
+                    union_maybe(&mut target.continued, &flow.maybe);
+                }
+            }
+        }
+        flow.diverges = true;
+    }
+
+    /// The body of a loop may run any number of times, so it is checked a second time with
+    /// everything the first pass may have assigned, and the loop may also be skipped entirely
+    /// unless it is `endless`.
+    fn loop_body(
+        &mut self,
+        children: &Vec<StatementNode>,
+        dec: Option<&IdentifierNode>,
+        label: &Option<LoopLabel>,
+        endless: bool,
+        flow: &mut Flow,
+    ) {
+        let quiet = std::mem::replace(&mut self.vars.quiet, true);
+        let (repeat, _) = self.loop_pass(children, dec, label, flow.clone());
+        self.vars.quiet = quiet;
+
+        let mut entry = flow.clone();
+        union_maybe(&mut entry.maybe, &repeat);
+        let (repeat, broken) = self.loop_pass(children, dec, label, entry.clone());
+        if endless {
+            *flow = broken;
+            return;
+        }
+        union_maybe(&mut entry.maybe, &repeat);
+        if !broken.diverges {
+            union_maybe(&mut entry.maybe, &broken.maybe);
+        }
+        *flow = entry;
+    }
+
+    /// Runs one iteration of a loop body. Returns what may be assigned when the body goes back
+    /// to the start of the loop, and the state when it breaks out of it.
+    fn loop_pass(
+        &mut self,
+        children: &Vec<StatementNode>,
+        dec: Option<&IdentifierNode>,
+        label: &Option<LoopLabel>,
+        mut flow: Flow,
+    ) -> (Vec<bool>, Flow) {
+        let mut broken = flow.clone();
+        broken.diverges = true;
+        self.loops.push(LoopExits {
+            label: label.clone(),
+            continued: flow.maybe.clone(),
+            broken,
+        });
+        self.vars.enter();
+        if let Some(dec) = dec {
+            self.declare(dec, true, &mut flow);
+        }
+        self.block(children, &mut flow);
+        self.vars.leave();
+        let mut exits = self.loops.pop().unwrap();
+        if !flow.diverges {
+            union_maybe(&mut exits.continued, &flow.maybe);
+        }
+        (exits.continued, exits.broken)
+    }
+
+    fn function_body(&mut self, func: &Function, outer: &Flow) {
+        self.body(format!("function {}", func.name), func, outer);
+    }
+
+    /// A lambda reads the variables it captures when it is created.
+    fn lambda(&mut self, lambda: &LambdaNode, flow: &Flow) {
+        for capture in &lambda.captures {
+            self.read(&capture.name, &lambda.span, flow);
+        }
+        self.body(
+            format!("the lambda on line {}", lambda.span.line),
+            &lambda.func,
+            flow,
+        );
+    }
+
+    fn body(&mut self, name: String, func: &Function, outer: &Flow) {
+        let enclosing = std::mem::replace(&mut self.function, name);
+        // Bindings of the enclosing scope are visible, but nothing is known about when they run
+        let outerloops = std::mem::take(&mut self.loops);
+        let mut flow = outer.clone();
+        flow.definite.iter_mut().for_each(|d| *d = true);
+        flow.maybe.iter_mut().for_each(|m| *m = true);
+        flow.diverges = false;
+        self.vars.enter();
+        for param in &func.params {
+            self.declare(param, true, &mut flow);
+        }
+        self.block(&func.body.children, &mut flow);
+        if func.ret != Types::Nothing && !flow.diverges {
+            let message = format!(
+                "In {}: Not every path returns a value of type {}",
+                self.function, func.ret
+            );
+            let diagnostic = Diagnostic::error(ErrorCode::Return, &func.span, message);
+            self.vars.report(diagnostic);
+        }
+        self.vars.leave();
+        self.function = enclosing;
+        self.loops = outerloops;
+    }
+
+    fn value(&mut self, value: &Value, flow: &Flow) {
+        match value {
+            Value::Lit(_) | Value::Func(_) | Value::Type(_) | Value::Nothing => {}
+            Value::Ident(node) | Value::Deref(node) => self.read(&node.name, &node.span, flow),
+            Value::Expr(Expression::Num(num)) => self.number(num, flow),
+            Value::Expr(Expression::Bool(cond)) => self.boolean(cond, flow),
+            Value::Expr(Expression::Text(concat)) => {
+                self.value(&concat.left, flow);
+                self.value(&concat.right, flow);
+            }
+            Value::Call(node) => self.call(node, flow),
+            Value::Lambda(lambda) => self.lambda(lambda, flow),
+            Value::Pointer(inner, _) => self.value(inner, flow),
+            Value::Vec(lit) => {
+                for element in &lit.elements {
+                    self.value(element, flow);
+                }
+            }
+            Value::Array(lit) => self.value(&lit.value, flow),
+            Value::Index(node) => self.index(node, flow),
+            Value::Struct(lit) => {
+                for field in &lit.fields {
+                    self.value(field, flow);
+                }
+            }
+            Value::Variant(lit) => {
+                for field in &lit.fields {
+                    self.value(field, flow);
+                }
+            }
+            Value::Field(node) => self.read(&node.target.name, &node.span, flow),
+        }
+    }
+
+    fn number(&mut self, num: &Number, flow: &Flow) {
+        match num {
+            Number::Lit(_) => {}
+            Number::Exp(expr) => {
+                self.number(&expr.left, flow);
+                self.number(&expr.right, flow);
+            }
+            Number::Neg(inner, _) => self.number(inner, flow),
+            Number::Ident(node) | Number::Deref(node) => self.read(&node.name, &node.span, flow),
+            Number::Index(node) => self.index(node, flow),
+            Number::Field(node) => self.read(&node.target.name, &node.span, flow),
+            Number::Call(node) => self.call(node, flow),
+        }
+    }
+
+    fn boolean(&mut self, cond: &Bool, flow: &Flow) {
+        match cond {
+            Bool::Lit(..) => {}
+            Bool::Expr(expr) => self.bool_expr(expr, flow),
+            Bool::Not(inner, _) => self.boolean(inner, flow),
+            Bool::Ident(node) | Bool::Deref(node) => self.read(&node.name, &node.span, flow),
+            Bool::Index(node) => self.index(node, flow),
+            Bool::Field(node) => self.read(&node.target.name, &node.span, flow),
+            Bool::Call(node) => self.call(node, flow),
+        }
+    }
+
+    fn index(&mut self, node: &IndexNode, flow: &Flow) {
+        self.read(&node.vec.name, &node.span, flow);
+        self.number(&node.index, flow);
+    }
+
+    fn bool_expr(&mut self, expr: &BoolExpr, flow: &Flow) {
+        self.value(&expr.left, flow);
+        self.value(&expr.right, flow);
+    }
+
+    fn call(&mut self, node: &CallNode, flow: &Flow) {
+        // Calling through a variable holding a function reads it
+        self.read(&node.func.name, &node.span, flow);
+        // Arguments are wrapped in nameless identifiers holding the passed value
+        for param in &node.params {
+            if let Some(value) = &param.value {
+                self.value(value, flow);
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::ops::{Index, IndexMut};
+
+use crate::diagnostics::diagnostic::{Diagnostic, ErrorCode, Span};
+use crate::parsing::nodes::IdentifierNode;
+
+/// The bindings of the program as a pass over the function bodies meets them, and the blocks
+/// they are visible in. A binding is known by the index it was declared under, and the pass
+/// keeps whatever it follows about it in `T`.
+pub struct Bindings<T> {
+    vars: Vec<Binding<T>>,
+    scopes: Vec<Vec<usize>>,
+    /// Every error found so far. Checking goes on after one, so all of them are reported.
+    diagnostics: Vec<Diagnostic>,
+    /// Suppresses errors while a loop body is checked for what it may do.
+    pub quiet: bool,
+}
+
+pub struct Binding<T> {
+    pub name: String,
+    pub declared: Span,
+    /// Number of blocks enclosing the binding, which lives until the innermost one ends.
+    pub depth: usize,
+    pub info: T,
+}
+
+impl<T> Bindings<T> {
+    pub fn new() -> Self {
+        Bindings {
+            vars: Vec::new(),
+            scopes: vec![Vec::new()],
+            diagnostics: Vec::new(),
+            quiet: false,
+        }
+    }
+
+    pub fn declare(&mut self, ident: &IdentifierNode, info: T) -> usize {
+        let id = self.vars.len();
+        self.vars.push(Binding {
+            name: ident.name.clone(),
+            declared: ident.declared.clone(),
+            depth: self.scopes.len(),
+            info,
+        });
+        self.scopes.last_mut().unwrap().push(id);
+        id
+    }
+
+    /// The innermost binding called `name`, if it is tracked.
+    pub fn resolve(&self, name: &String) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|id| self.vars[**id].name == *name)
+            .copied()
+    }
+
+    pub fn enter(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    pub fn leave(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Number of blocks around the code being checked.
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Number of bindings declared so far, which is the index of the next one.
+    pub fn count(&self) -> usize {
+        self.vars.len()
+    }
+
+    /// Forgets every binding from index `count` on, so checking a loop body again declares
+    /// its bindings under the same indices.
+    pub fn forget(&mut self, count: usize) {
+        self.vars.truncate(count);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Binding<T>> {
+        self.vars.iter()
+    }
+
+    /// Reports `diagnostic`, unless errors are suppressed.
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        if !self.quiet {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Reports `diagnostic` about the binding `id`, pointing at its declaration as well.
+    pub fn report_at(&mut self, diagnostic: Diagnostic, id: usize) {
+        let var = &self.vars[id];
+        let label = format!("{} is declared here", var.name);
+        let diagnostic = diagnostic.with_label(&var.declared, label);
+        self.report(diagnostic);
+    }
+
+    pub fn finish(self) -> Result<(), Vec<Diagnostic>> {
+        if self.diagnostics.is_empty() {
+            Ok(())
+        } else {
+            Err(self.diagnostics)
+        }
+    }
+}
+
+impl<T> Index<usize> for Bindings<T> {
+    type Output = Binding<T>;
+
+    fn index(&self, id: usize) -> &Binding<T> {
+        &self.vars[id]
+    }
+}
+
+impl<T> IndexMut<usize> for Bindings<T> {
+    fn index_mut(&mut self, id: usize) -> &mut Binding<T> {
+        &mut self.vars[id]
+    }
+}
+
+/// The error for assigning `name` at `span` once it may have a value already, worded the same
+/// whichever pass finds it.
+pub fn assigned_twice(name: &String, span: &Span, declared: &Span) -> Diagnostic {
+    Diagnostic::error(
+        ErrorCode::Immutable,
+        span,
+        format!("Cannot assign twice to immutable variable {name}"),
+    )
+    .with_label(
+        declared,
+        format!("{name} is declared here. Declare it with 'let mut' to make it reassignable"),
+    )
+}
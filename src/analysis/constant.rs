@@ -0,0 +1,230 @@
+use crate::parsing::nodes::*;
+
+/// Compile time evaluation. A value is constant if it is built from literals and immutable
+/// bindings holding constants, combined with operators. Num arithmetic is done like C does it
+/// on an int, except that overflowing is an error rather than undefined. A literal worked out
+/// from an expression gets the span of that expression.
+pub enum Folded {
+    Const(Literal),
+    /// Only known at runtime, because of the part of the value described.
+    Runtime(String),
+    /// Would fail at runtime, for the reason described.
+    Invalid(String),
+}
+
+pub fn evaluate(value: &Value) -> Folded {
+    match value {
+        Value::Lit(lit) => Folded::Const(lit.clone()),
+        Value::Ident(node) => binding(node),
+        Value::Expr(Expression::Num(num)) => number(num),
+        Value::Expr(Expression::Bool(cond)) => boolean(cond),
+        Value::Expr(Expression::Text(concat)) => {
+            let left = match text(&concat.left) {
+                Ok(left) => left,
+                Err(folded) => return folded,
+            };
+            let right = match text(&concat.right) {
+                Ok(right) => right,
+                Err(folded) => return folded,
+            };
+            Folded::Const(Literal::Text(TextLit {
+                value: left + &right,
+                span: concat.span.clone(),
+            }))
+        }
+        Value::Call(node) => Folded::Runtime(format!("the call to {}", node.func.name)),
+        Value::Deref(node) => Folded::Runtime(format!("what {} points to", node.name)),
+        Value::Index(node) => Folded::Runtime(format!("an element of {}", node.vec.name)),
+        Value::Field(node) => Folded::Runtime(format!("a field of {}", node.target.name)),
+        Value::Pointer(..) => Folded::Runtime(String::from("a reference")),
+        Value::Func(_) | Value::Lambda(_) => Folded::Runtime(String::from("a function")),
+        Value::Vec(_) => Folded::Runtime(String::from("a Vec")),
+        Value::Array(_) => Folded::Runtime(String::from("an array")),
+        Value::Struct(_) | Value::Variant(_) => Folded::Runtime(String::from("a struct")),
+        Value::Type(_) | Value::Nothing => Folded::Runtime(String::from("a type")),
+    }
+}
+
+/// An immutable binding is assigned once, so one declared with a constant value holds it for
+/// good. Bindings declared without a value have none recorded.
+fn binding(node: &IdentifierNode) -> Folded {
+    if node.mutable {
+        return Folded::Runtime(format!("the mutable variable {}", node.name));
+    }
+    match node.value.as_deref() {
+        Some(value) => match evaluate(value) {
+            Folded::Runtime(_) => Folded::Runtime(format!("the variable {}", node.name)),
+            folded => folded,
+        },
+        None => Folded::Runtime(format!("the variable {}", node.name)),
+    }
+}
+
+pub fn number(num: &Number) -> Folded {
+    let folded = match num {
+        Number::Lit(lit) => return Folded::Const(Literal::Num(lit.clone())),
+        Number::Ident(node) => return binding(node),
+        Number::Exp(expr) => {
+            let left = match as_num(number(&expr.left)) {
+                Ok(left) => left,
+                Err(folded) => return folded,
+            };
+            let right = match as_num(number(&expr.right)) {
+                Ok(right) => right,
+                Err(folded) => return folded,
+            };
+            let (result, sign) = match expr.operator {
+                Operators::Plus => (left.checked_add(right), "+"),
+                Operators::Minus => (left.checked_sub(right), "-"),
+                Operators::Mul => (left.checked_mul(right), "*"),
+                Operators::Div | Operators::Mod if right == 0 => {
+                    return Folded::Invalid(format!("Cannot divide {left} by zero"));
+                }
+                Operators::Div => (left.checked_div(right), "/"),
+                Operators::Mod => (left.checked_rem(right), "%"),
+            };
+            result.ok_or(format!("{left} {sign} {right} does not fit in a Num"))
+        }
+        Number::Neg(inner, _) => {
+            let inner = match as_num(number(inner)) {
+                Ok(inner) => inner,
+                Err(folded) => return folded,
+            };
+            inner
+                .checked_neg()
+                .ok_or(format!("-({inner}) does not fit in a Num"))
+        }
+        other => return evaluate(&other.clone().to_value()),
+    };
+    match folded {
+        Ok(val) => Folded::Const(Literal::Num(NumLiteral {
+            val,
+            span: num.span().clone(),
+        })),
+        Err(message) => Folded::Invalid(message),
+    }
+}
+
+pub fn boolean(cond: &Bool) -> Folded {
+    let result = match cond {
+        Bool::Lit(lit, span) => return Folded::Const(Literal::Bool(lit.clone(), span.clone())),
+        Bool::Ident(node) => return binding(node),
+        Bool::Not(inner, _) => match as_bool(boolean(inner)) {
+            Ok(inner) => !inner,
+            Err(folded) => return folded,
+        },
+        Bool::Expr(expr) => match bool_expr(expr) {
+            Ok(result) => result,
+            Err(folded) => return folded,
+        },
+        other => return evaluate(&other.clone().to_value()),
+    };
+    let lit = if result {
+        BoolLiteral::True
+    } else {
+        BoolLiteral::False
+    };
+    Folded::Const(Literal::Bool(lit, cond.span().clone()))
+}
+
+fn bool_expr(expr: &BoolExpr) -> Result<bool, Folded> {
+    let left = evaluate(&expr.left);
+    // The right side is not evaluated at runtime once the left side decides the result
+    match (&expr.operator, &left) {
+        (BoolOps::And, Folded::Const(Literal::Bool(BoolLiteral::False, _))) => return Ok(false),
+        (BoolOps::Or, Folded::Const(Literal::Bool(BoolLiteral::True, _))) => return Ok(true),
+        _ => {}
+    }
+    let left = as_const(left)?;
+    let right = as_const(evaluate(&expr.right))?;
+    let result = match (left, right) {
+        (Literal::Num(left), Literal::Num(right)) => {
+            let (left, right) = (left.val, right.val);
+            match expr.operator {
+                BoolOps::EQ => left == right,
+                BoolOps::NotEq => left != right,
+                BoolOps::Greater => left > right,
+                BoolOps::Lesser => left < right,
+                BoolOps::GreaterEq => left >= right,
+                BoolOps::LesserEq => left <= right,
+                BoolOps::And | BoolOps::Or => return Err(Folded::Runtime(String::new())),
+            }
+        }
+        (Literal::Bool(left, _), Literal::Bool(right, _)) => {
+            let (left, right) = (left == BoolLiteral::True, right == BoolLiteral::True);
+            match expr.operator {
+                BoolOps::EQ => left == right,
+                BoolOps::NotEq => left != right,
+                BoolOps::And => left && right,
+                BoolOps::Or => left || right,
+                _ => return Err(Folded::Runtime(String::new())),
+            }
+        }
+        (Literal::Text(left), Literal::Text(right)) => {
+            // Escape sequences are kept as written, so they could compare unlike their text
+            if left.value.contains('\\') || right.value.contains('\\') {
+                return Err(Folded::Runtime(String::from(
+                    "a comparison of Text with escape sequences",
+                )));
+            }
+            match expr.operator {
+                BoolOps::EQ => left.value == right.value,
+                BoolOps::NotEq => left.value != right.value,
+                _ => return Err(Folded::Runtime(String::new())),
+            }
+        }
+        _ => return Err(Folded::Runtime(String::new())),
+    };
+    Ok(result)
+}
+
+fn as_const(folded: Folded) -> Result<Literal, Folded> {
+    match folded {
+        Folded::Const(lit) => Ok(lit),
+        other => Err(other),
+    }
+}
+
+fn as_num(folded: Folded) -> Result<i32, Folded> {
+    match as_const(folded)? {
+        Literal::Num(lit) => Ok(lit.val),
+        _ => Err(Folded::Runtime(String::new())),
+    }
+}
+
+fn as_bool(folded: Folded) -> Result<bool, Folded> {
+    match as_const(folded)? {
+        Literal::Bool(lit, _) => Ok(lit == BoolLiteral::True),
+        _ => Err(Folded::Runtime(String::new())),
+    }
+}
+
+/// An array literal holds copies of a single element, so a constant array is its literal with
+/// that element worked out.
+pub fn array(value: &Value) -> Result<ArrayLiteral, Folded> {
+    match value {
+        Value::Array(lit) => {
+            let element = as_const(evaluate(&lit.value))?;
+            Ok(ArrayLiteral {
+                value: Box::new(Value::Lit(element)),
+                ..lit.clone()
+            })
+        }
+        Value::Ident(node) if !node.mutable => match node.value.as_deref().map(array) {
+            Some(Ok(lit)) => Ok(lit),
+            Some(Err(Folded::Invalid(message))) => Err(Folded::Invalid(message)),
+            _ => Err(Folded::Runtime(format!("the variable {}", node.name))),
+        },
+        other => match evaluate(other) {
+            Folded::Const(_) => Err(Folded::Runtime(String::from("an array"))),
+            folded => Err(folded),
+        },
+    }
+}
+
+fn text(value: &Value) -> Result<String, Folded> {
+    match as_const(evaluate(value))? {
+        Literal::Text(lit) => Ok(lit.value),
+        _ => Err(Folded::Runtime(String::new())),
+    }
+}
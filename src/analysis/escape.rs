@@ -0,0 +1,407 @@
+use crate::analysis::bindings::Bindings;
+use crate::diagnostics::diagnostic::{Diagnostic, ErrorCode, Span};
+use crate::parsing::nodes::*;
+
+/// Escape analysis. Memory in a function's stack frame is gone once the function returns, so a
+/// pointer into it may not be returned, or be written through a pointer that reaches outside of
+/// the function. Likewise a binding is gone at the end of its block, so a pointer to it may not
+/// be assigned to a binding of an enclosing block. Text and Vecs are copied whenever they are
+/// stored or returned, so they never escape. A struct refers to everything its fields may point
+/// to, and a lambda to the variables it captured and to its environment.
+pub fn check(program: &Program) -> Result<(), Vec<Diagnostic>> {
+    let mut checker = Checker {
+        vars: Bindings::new(),
+        frame: 0,
+        frames: 0,
+        function: String::new(),
+    };
+    checker.block(&program.children);
+    for lambda in &program.lambdas {
+        checker.lambda_body(lambda);
+    }
+    checker.vars.finish()
+}
+
+/// Memory a pointer may refer to.
+#[derive(Clone, Copy, PartialEq)]
+enum Origin {
+    /// The storage of the binding with this index in `Checker::vars`.
+    Local(usize),
+    /// Memory owned by a caller, or static memory such as a Text literal.
+    Outside,
+    /// The environment of a capturing lambda, stored in the block of this stack frame and
+    /// nesting depth it was created in.
+    Env { frame: usize, depth: usize },
+}
+
+/// What is known about a binding.
+struct Var {
+    /// Stack frame the binding is stored in.
+    frame: usize,
+    /// Everything this binding may refer to, if it is a pointer.
+    points: Vec<Origin>,
+}
+
+struct Checker {
+    vars: Bindings<Var>,
+    frame: usize,
+    /// Number of stack frames so far, used to give every function body its own.
+    frames: usize,
+    function: String,
+}
+
+fn add_origins(target: &mut Vec<Origin>, origins: Vec<Origin>) {
+    for origin in origins {
+        if !target.contains(&origin) {
+            target.push(origin);
+        }
+    }
+}
+
+impl Checker {
+    fn declare(&mut self, ident: &IdentifierNode, points: Vec<Origin>) {
+        let frame = self.frame;
+        self.vars.declare(ident, Var { frame, points });
+    }
+
+    /// The first binding of the current stack frame that `origins` refers to.
+    fn local(&self, origins: &Vec<Origin>) -> Option<usize> {
+        origins.iter().find_map(|origin| match origin {
+            Origin::Local(id) if self.vars[*id].info.frame == self.frame => Some(*id),
+            _ => None,
+        })
+    }
+
+    fn error(&mut self, span: &Span, message: String, id: usize) {
+        let diagnostic = Diagnostic::error(ErrorCode::Escape, span, message);
+        self.vars.report_at(diagnostic, id);
+    }
+
+    fn scoped_block(&mut self, children: &Vec<StatementNode>) {
+        self.vars.enter();
+        self.block(children);
+        self.vars.leave();
+    }
+
+    fn block(&mut self, children: &Vec<StatementNode>) {
+        for child in children {
+            self.statement(child);
+        }
+    }
+
+    fn statement(&mut self, stmt: &StatementNode) {
+        match stmt {
+            StatementNode::DeclareAssign(node) => {
+                let Some(value) = node.ident.value.as_deref() else {
+                    return;
+                };
+                if let Value::Func(func) = value {
+                    self.function_body(func);
+                    self.declare(&node.ident, Vec::new());
+                    return;
+                }
+                let points = self.origins(value);
+                self.declare(&node.ident, points);
+            }
+            StatementNode::Declare(node) => self.declare(&node.ident, Vec::new()),
+            StatementNode::Assign(node) => self.assign(node),
+            StatementNode::Return(node) => {
+                let origins = self.origins(&node.value);
+                if let Some(id) = self.local(&origins) {
+                    let message = format!(
+                        "Cannot return {} {}, which only lives until {} returns",
+                        Self::holder(&node.value),
+                        self.vars[id].name,
+                        self.function
+                    );
+                    self.error(&node.span, message, id);
+                }
+            }
+            StatementNode::Conditional(node) => {
+                self.scoped_block(&node.body.children);
+                for elif in &node.elifs {
+                    self.scoped_block(&elif.body.children);
+                }
+                if let Some(block) = &node.i_else {
+                    self.scoped_block(&block.children);
+                }
+            }
+            StatementNode::ForLoop(node) => {
+                self.loop_body(&node.body.children, Some(&node.dec));
+            }
+            StatementNode::WhileLoop(node) => self.loop_body(&node.body.children, None),
+            StatementNode::Match(node) => {
+                // Bound fields are copies of the matched value, pointing wherever it points
+                let origins = self.origins(&node.value);
+                for arm in &node.arms {
+                    self.vars.enter();
+                    for (_, binding) in &arm.bindings {
+                        let points = match binding.i_type.holds_pointers() {
+                            true => origins.clone(),
+                            false => Vec::new(),
+                        };
+                        self.declare(binding, points);
+                    }
+                    self.block(&arm.body.children);
+                    self.vars.leave();
+                }
+            }
+            StatementNode::Call(_)
+            | StatementNode::Inline(_)
+            | StatementNode::Break(_)
+            | StatementNode::Continue(_) => {}
+        }
+    }
+
+    fn assign(&mut self, node: &AssignNode) {
+        let Some(value) = node.ident.value.as_deref() else {
+            return;
+        };
+        let origins = self.origins(value);
+        let Some(target) = self.vars.resolve(&node.ident.name) else {
+            return;
+        };
+        // Fields of a struct behind a pointer are written through that pointer
+        let through =
+            node.deref || (!node.fields.is_empty() && node.ident.i_type.pointee().is_some());
+        if !through {
+            self.outlives(node, value, target, &origins);
+            add_origins(&mut self.vars[target].info.points, origins);
+            return;
+        }
+        // Writing through a pointer that may reach outside hands the value to a caller
+        if !self.vars[target].info.points.contains(&Origin::Outside) {
+            return;
+        }
+        if let Some(id) = self.local(&origins) {
+            let message = format!(
+                "Cannot store {} {} through {}, which points outside of {}",
+                Self::holder(value),
+                self.vars[id].name,
+                node.ident.name,
+                self.function
+            );
+            self.error(&node.span, message, id);
+        }
+    }
+
+    /// Rejects assigning to `target` anything of the current stack frame that is gone before
+    /// the end of the block `target` is declared in.
+    fn outlives(&mut self, node: &AssignNode, value: &Value, target: usize, origins: &[Origin]) {
+        let depth = self.vars[target].depth;
+        for origin in origins {
+            match *origin {
+                Origin::Local(id) if self.vars[id].info.frame == self.frame => {
+                    if self.vars[id].depth > depth {
+                        let message = format!(
+                            "Cannot assign {} {} to {}, which outlives it",
+                            Self::holder(value),
+                            self.vars[id].name,
+                            node.ident.name
+                        );
+                        self.error(&node.span, message, id);
+                        return;
+                    }
+                }
+                Origin::Env { frame, depth: env } if frame == self.frame && env > depth => {
+                    let message = format!(
+                        "Cannot assign a lambda created in this block to {}, which outlives \
+                         the copies it captured",
+                        node.ident.name
+                    );
+                    let diagnostic = Diagnostic::error(ErrorCode::Escape, &node.span, message);
+                    self.vars.report_at(diagnostic, target);
+                    return;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// A loop body may run any number of times, so it is checked quietly until no binding can
+    /// point anywhere new, and only then reports what it finds.
+    fn loop_body(&mut self, children: &Vec<StatementNode>, dec: Option<&IdentifierNode>) {
+        let quiet = std::mem::replace(&mut self.vars.quiet, true);
+        loop {
+            let before = self.pointed();
+            self.loop_pass(children, dec);
+            if self.pointed() == before {
+                break;
+            }
+        }
+        self.vars.quiet = quiet;
+        self.loop_pass(children, dec);
+    }
+
+    /// Runs one iteration of a loop body. Bindings declared inside it are declared again on
+    /// every pass, under the same indices, so what they are pointed to by stays comparable.
+    fn loop_pass(&mut self, children: &Vec<StatementNode>, dec: Option<&IdentifierNode>) {
+        let (count, frames) = (self.vars.count(), self.frames);
+        self.vars.enter();
+        if let Some(dec) = dec {
+            self.declare(dec, Vec::new());
+        }
+        self.block(children);
+        self.vars.leave();
+        if self.vars.quiet {
+            self.vars.forget(count);
+            self.frames = frames;
+        }
+    }
+
+    /// How many places each binding may point to. Bindings only ever gain places, so this
+    /// stays the same exactly when nothing changed.
+    fn pointed(&self) -> Vec<usize> {
+        self.vars.iter().map(|var| var.info.points.len()).collect()
+    }
+
+    /// How an error names a value referring to a binding, which for functions is a lambda
+    /// having captured it.
+    fn holder(value: &Value) -> &'static str {
+        match value.get_type() {
+            Some(Types::Function(_)) => "a lambda capturing",
+            _ => "a pointer to",
+        }
+    }
+
+    fn function_body(&mut self, func: &Function) {
+        self.body(func.name.clone(), func, &Vec::new());
+    }
+
+    /// A lambda runs in its own stack frame, holding a copy of every variable it captured.
+    /// Lambdas are checked after the rest of the program, as a lambda that outlives the
+    /// variables it captured is already an error where it is created.
+    fn lambda_body(&mut self, lambda: &LambdaNode) {
+        let name = format!("the lambda on line {}", lambda.span.line);
+        self.body(name, &lambda.func, &lambda.captures);
+    }
+
+    fn body(&mut self, name: String, func: &Function, captures: &Vec<IdentifierNode>) {
+        self.frames += 1;
+        let enclosing = std::mem::replace(&mut self.frame, self.frames);
+        let name = std::mem::replace(&mut self.function, name);
+        self.vars.enter();
+        for param in captures.iter().chain(&func.params) {
+            // Parameters and captures are copies in this frame, but what they point to lives
+            // outside of it
+            self.declare(param, vec![Origin::Outside]);
+        }
+        self.block(&func.body.children);
+        self.vars.leave();
+        self.function = name;
+        self.frame = enclosing;
+    }
+
+    /// Everything a pointer value may refer to. Values of other types are copied and never
+    /// refer to anything.
+    fn origins(&self, value: &Value) -> Vec<Origin> {
+        match value {
+            Value::Ident(node) => {
+                if !node.i_type.holds_pointers() {
+                    return Vec::new();
+                }
+                match self.vars.resolve(&node.name) {
+                    Some(id) => self.vars[id].info.points.clone(),
+                    None => Vec::new(),
+                }
+            }
+            Value::Pointer(target, _) => match target.as_ref() {
+                Value::Ident(node) => match self.vars.resolve(&node.name) {
+                    Some(id) => vec![Origin::Local(id)],
+                    None => vec![Origin::Outside],
+                },
+                _ => vec![Origin::Outside],
+            },
+            Value::Deref(node) => {
+                if !node
+                    .i_type
+                    .pointee()
+                    .is_some_and(|inner| inner.holds_pointers())
+                {
+                    return Vec::new();
+                }
+                self.pointed_origins(node)
+            }
+            Value::Field(node) => {
+                if !node.i_type.holds_pointers() {
+                    return Vec::new();
+                }
+                if node.target.i_type.pointee().is_some() {
+                    return self.pointed_origins(&node.target);
+                }
+                match self.vars.resolve(&node.target.name) {
+                    Some(id) => self.vars[id].info.points.clone(),
+                    None => Vec::new(),
+                }
+            }
+            Value::Struct(lit) => {
+                let mut origins = Vec::new();
+                for field in &lit.fields {
+                    add_origins(&mut origins, self.origins(field));
+                }
+                origins
+            }
+            Value::Variant(lit) => {
+                let mut origins = Vec::new();
+                for field in &lit.fields {
+                    add_origins(&mut origins, self.origins(field));
+                }
+                origins
+            }
+            // The environment of the lambda is stored in the block creating it, holding copies
+            // of the variables it captured
+            Value::Lambda(lambda) => {
+                if lambda.captures.is_empty() {
+                    return Vec::new();
+                }
+                let mut origins = vec![Origin::Env {
+                    frame: self.frame,
+                    depth: self.vars.depth(),
+                }];
+                for capture in &lambda.captures {
+                    if let Some(id) = self.vars.resolve(&capture.name) {
+                        add_origins(&mut origins, vec![Origin::Local(id)]);
+                    }
+                    add_origins(&mut origins, self.origins(&Value::Ident(capture.clone())));
+                }
+                origins
+            }
+            Value::Call(node) => {
+                if !node.func.ret.holds_pointers() {
+                    return Vec::new();
+                }
+                // The result may be any of the arguments handed back
+                let mut origins = vec![Origin::Outside];
+                for param in &node.params {
+                    if let Some(arg) = param.value.as_deref() {
+                        add_origins(&mut origins, self.origins(arg));
+                    }
+                }
+                origins
+            }
+            Value::Lit(_)
+            | Value::Expr(_)
+            | Value::Func(_)
+            | Value::Vec(_)
+            | Value::Array(_)
+            | Value::Index(_)
+            | Value::Type(_)
+            | Value::Nothing => Vec::new(),
+        }
+    }
+
+    /// Reads whatever the bindings the pointer `node` may point to hold.
+    fn pointed_origins(&self, node: &IdentifierNode) -> Vec<Origin> {
+        let Some(ptr) = self.vars.resolve(&node.name) else {
+            return vec![Origin::Outside];
+        };
+        let mut origins = Vec::new();
+        for origin in &self.vars[ptr].info.points {
+            match origin {
+                Origin::Local(id) => add_origins(&mut origins, self.vars[*id].info.points.clone()),
+                Origin::Outside | Origin::Env { .. } => add_origins(&mut origins, vec![*origin]),
+            }
+        }
+        origins
+    }
+}
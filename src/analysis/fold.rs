@@ -0,0 +1,291 @@
+use crate::analysis::constant::{self, Folded};
+use crate::diagnostics::diagnostic::{Diagnostic, ErrorCode};
+use crate::parsing::nodes::*;
+
+/// Constant folding. Every Num and Bool expression that is known at compile time is replaced
+/// by its value, and the code that can never run is removed: the branches of a conditional
+/// whose condition is known, loops that never start, and whatever follows a return, break or
+/// continue. Runs after the checks, which see the program as it was written. Arithmetic known
+/// to divide by zero or to overflow is an error only in the code that is left, so a guard or a
+/// short-circuiting `&&` can keep it from running.
+pub fn fold(program: &mut Program) -> Result<(), Vec<Diagnostic>> {
+    let mut errors = Vec::new();
+    let errors = &mut errors;
+    for child in program.children.iter_mut() {
+        if let StatementNode::DeclareAssign(node) = child {
+            if let Some(Value::Func(func)) = node.ident.value.as_deref_mut() {
+                block(&mut func.body.children, errors);
+            }
+        }
+    }
+    for lambda in program.lambdas.iter_mut() {
+        block(&mut lambda.func.body.children, errors);
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(std::mem::take(errors))
+    }
+}
+
+fn block(children: &mut Vec<StatementNode>, errors: &mut Vec<Diagnostic>) {
+    let old = std::mem::take(children);
+    for mut child in old {
+        match child {
+            StatementNode::Conditional(node) => {
+                // Nothing is left of a conditional none of whose branches can run
+                let Some(node) = conditional(node, errors) else {
+                    continue;
+                };
+                child = StatementNode::Conditional(node);
+            }
+            StatementNode::WhileLoop(ref node) if known(&node.condition) == Some(false) => {
+                continue;
+            }
+            _ => statement(&mut child, errors),
+        }
+        let ends = diverges(&child);
+        children.push(child);
+        if ends {
+            break;
+        }
+    }
+}
+
+/// Whether the code after `stmt` never runs, once `stmt` is folded.
+fn diverges(stmt: &StatementNode) -> bool {
+    match stmt {
+        StatementNode::Return(_) | StatementNode::Break(_) | StatementNode::Continue(_) => true,
+        // One of the branches runs if there is an else, or if the first one always does
+        StatementNode::Conditional(node) => {
+            (node.i_else.is_some() || matches!(node.condition, Bool::Lit(BoolLiteral::True, _)))
+                && ends(&node.body)
+                && node.elifs.iter().all(|elif| ends(&elif.body))
+                && node.i_else.iter().all(ends)
+        }
+        _ => false,
+    }
+}
+
+/// Whether a folded block never lets the code after it run, which only its last statement can
+/// decide.
+fn ends(body: &BlockNode) -> bool {
+    body.children.last().is_some_and(diverges)
+}
+
+fn statement(child: &mut StatementNode, errors: &mut Vec<Diagnostic>) {
+    match child {
+        StatementNode::DeclareAssign(node) => {
+            if let Some(value) = node.ident.value.as_deref_mut() {
+                fold_value(value, errors);
+            }
+        }
+        StatementNode::Assign(node) => {
+            if let Some(value) = node.ident.value.as_deref_mut() {
+                fold_value(value, errors);
+            }
+            if let Some(index) = &mut node.index {
+                number(index, errors);
+            }
+        }
+        StatementNode::Call(node) => call(node, errors),
+        StatementNode::Return(node) => fold_value(&mut node.value, errors),
+        StatementNode::ForLoop(node) => {
+            match &mut node.iter {
+                Iterable::Range(range) => {
+                    number(&mut range.start, errors);
+                    number(&mut range.end, errors);
+                }
+                Iterable::Elements(value) => fold_value(value, errors),
+            }
+            block(&mut node.body.children, errors);
+        }
+        StatementNode::WhileLoop(node) => {
+            boolean(&mut node.condition, errors);
+            block(&mut node.body.children, errors);
+        }
+        StatementNode::Match(node) => {
+            fold_value(&mut node.value, errors);
+            for arm in node.arms.iter_mut() {
+                block(&mut arm.body.children, errors);
+            }
+        }
+        StatementNode::Conditional(_)
+        | StatementNode::Declare(_)
+        | StatementNode::Inline(_)
+        | StatementNode::Break(_)
+        | StatementNode::Continue(_) => {}
+    }
+}
+
+/// Drops every branch that can never run, and every branch after one that always runs, which
+/// becomes the else branch. A conditional left with only a branch that always runs has the
+/// condition `True`, and is written out as a plain block.
+fn conditional(node: ConditionalNode, errors: &mut Vec<Diagnostic>) -> Option<ConditionalNode> {
+    let mut branches = vec![(node.condition, node.body)];
+    branches.extend(
+        node.elifs
+            .into_iter()
+            .map(|elif| (elif.condition, elif.body)),
+    );
+    let mut kept: Vec<(Bool, BlockNode)> = Vec::new();
+    let mut always = false;
+    for (mut condition, mut body) in branches {
+        match known(&condition) {
+            Some(false) => continue,
+            Some(true) => condition = Bool::Lit(BoolLiteral::True, condition.span().clone()),
+            None => boolean(&mut condition, errors),
+        }
+        block(&mut body.children, errors);
+        let decided = matches!(condition, Bool::Lit(BoolLiteral::True, _));
+        kept.push((condition, body));
+        if decided {
+            always = true;
+            break;
+        }
+    }
+    let mut i_else = node.i_else;
+    if always {
+        i_else = None;
+        if kept.len() > 1 {
+            i_else = kept.pop().map(|(_, body)| body);
+        }
+    } else if let Some(els) = &mut i_else {
+        block(&mut els.children, errors);
+    }
+    if kept.is_empty() {
+        let els = i_else.take()?;
+        kept.push((Bool::Lit(BoolLiteral::True, els.span.clone()), els));
+    }
+    let mut kept = kept.into_iter();
+    let (condition, body) = kept.next()?;
+    Some(ConditionalNode {
+        condition,
+        body,
+        elifs: kept
+            .map(|(condition, body)| ElseIfNode {
+                span: condition.span().to(&body.span),
+                condition,
+                body,
+            })
+            .collect(),
+        i_else,
+        span: node.span,
+    })
+}
+
+/// The value of a condition, if it is known at compile time.
+fn known(condition: &Bool) -> Option<bool> {
+    match constant::boolean(condition) {
+        Folded::Const(Literal::Bool(lit, _)) => Some(lit == BoolLiteral::True),
+        _ => None,
+    }
+}
+
+fn fold_value(value: &mut Value, errors: &mut Vec<Diagnostic>) {
+    match value {
+        Value::Expr(Expression::Num(num)) => {
+            number(num, errors);
+            if let Number::Lit(lit) = num {
+                *value = Value::Lit(Literal::Num(lit.clone()));
+            }
+        }
+        Value::Expr(Expression::Bool(cond)) => {
+            boolean(cond, errors);
+            if let Bool::Lit(lit, span) = cond {
+                *value = Value::Lit(Literal::Bool(lit.clone(), span.clone()));
+            }
+        }
+        Value::Expr(Expression::Text(concat)) => {
+            fold_value(&mut concat.left, errors);
+            fold_value(&mut concat.right, errors);
+        }
+        Value::Call(node) => call(node, errors),
+        Value::Index(node) => number(&mut node.index, errors),
+        Value::Vec(lit) => {
+            for value in lit.elements.iter_mut() {
+                fold_value(value, errors);
+            }
+        }
+        Value::Array(lit) => fold_value(&mut lit.value, errors),
+        Value::Struct(lit) => {
+            for value in lit.fields.iter_mut() {
+                fold_value(value, errors);
+            }
+        }
+        Value::Variant(lit) => {
+            for value in lit.fields.iter_mut() {
+                fold_value(value, errors);
+            }
+        }
+        // Reads of bindings are kept, only the expressions combining them are folded. A
+        // reference has to keep pointing at its variable.
+        Value::Lit(_)
+        | Value::Ident(_)
+        | Value::Pointer(..)
+        | Value::Deref(_)
+        | Value::Field(_)
+        | Value::Func(_)
+        | Value::Lambda(_)
+        | Value::Type(_)
+        | Value::Nothing => {}
+    }
+}
+
+fn call(node: &mut CallNode, errors: &mut Vec<Diagnostic>) {
+    for param in node.params.iter_mut() {
+        if let Some(value) = param.value.as_deref_mut() {
+            fold_value(value, errors);
+        }
+    }
+}
+
+fn number(num: &mut Number, errors: &mut Vec<Diagnostic>) {
+    let folded = match num {
+        Number::Exp(_) | Number::Neg(..) => constant::number(num),
+        Number::Index(node) => return number(&mut node.index, errors),
+        Number::Call(node) => return call(node, errors),
+        _ => return,
+    };
+    if let Folded::Const(Literal::Num(lit)) = folded {
+        *num = Number::Lit(lit);
+        return;
+    }
+    let reported = errors.len();
+    match num {
+        Number::Exp(expr) => {
+            number(&mut expr.left, errors);
+            number(&mut expr.right, errors);
+        }
+        Number::Neg(inner, _) => number(inner, errors),
+        _ => {}
+    }
+    // Only the operation that goes wrong first is reported, not every one containing it
+    if let Folded::Invalid(message) = folded {
+        if errors.len() == reported {
+            errors.push(Diagnostic::error(ErrorCode::Constant, num.span(), message));
+        }
+    }
+}
+
+fn boolean(cond: &mut Bool, errors: &mut Vec<Diagnostic>) {
+    match cond {
+        Bool::Expr(_) | Bool::Not(..) => {
+            if let Folded::Const(Literal::Bool(lit, span)) = constant::boolean(cond) {
+                *cond = Bool::Lit(lit, span);
+                return;
+            }
+        }
+        Bool::Index(node) => return number(&mut node.index, errors),
+        Bool::Call(node) => return call(node, errors),
+        _ => return,
+    }
+    match cond {
+        Bool::Expr(expr) => {
+            fold_value(&mut expr.left, errors);
+            fold_value(&mut expr.right, errors);
+        }
+        Bool::Not(inner, _) => boolean(inner, errors),
+        _ => {}
+    }
+}
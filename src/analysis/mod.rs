@@ -0,0 +1,6 @@
+pub mod assignment;
+mod bindings;
+pub mod constant;
+pub mod escape;
+pub mod fold;
+pub mod typeck;
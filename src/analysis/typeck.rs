@@ -0,0 +1,2257 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::analysis::bindings::assigned_twice;
+use crate::analysis::constant::{self, Folded};
+use crate::diagnostics::diagnostic::{Diagnostic, ErrorCode, Span};
+use crate::parsing::nodes::*;
+use crate::parsing::syntax::*;
+
+/// Semantic analysis. Resolves every name in the syntax tree the parser built, checks the type
+/// of every expression and statement, and gives the typed program the other passes and the
+/// generator work on.
+pub fn check(program: &[Stmt]) -> Result<Program, Diagnostic> {
+    let mut checker = TypeChecker::new();
+    let children = checker.program(program)?;
+    Ok(Program {
+        children,
+        func_types: checker.func_types,
+        lambdas: checker.lambdas,
+    })
+}
+
+struct TypeChecker {
+    symbols: SymbolStack,
+    /// Loops enclosing the statement being checked, innermost last.
+    loops: Vec<Option<LoopLabel>>,
+    /// Number of labelled loops so far, used to keep their C labels unique.
+    label_count: usize,
+    /// Number of `match` arms around the statement being checked, inside the innermost loop.
+    match_depth: usize,
+    /// Number of `match` statements so far, used to keep their C variables unique.
+    match_count: usize,
+    /// Every function type used so far, in the order they were first used.
+    func_types: Vec<FuncType>,
+    /// Lambdas whose body is being checked, innermost last.
+    open_lambdas: Vec<LambdaScope>,
+    /// Every lambda checked so far, in the order their bodies ended.
+    lambdas: Vec<LambdaNode>,
+    /// Types declared at the top level that are not defined yet, with where they are declared.
+    /// Each is defined once it is first used, so types can use types declared further down.
+    pending_types: HashMap<String, (TypeDef, Span)>,
+    /// Types whose definition is being worked out, innermost last.
+    defining: Vec<String>,
+}
+
+/// A lambda whose body is being checked, collecting the variables it captures.
+struct LambdaScope {
+    /// Index of the first scope inside the lambda. Names found in scopes below it, other than
+    /// the top level, are captured.
+    base: usize,
+    captures: Vec<IdentifierNode>,
+}
+
+/// A type error. Each kind is worded the same wherever it is found.
+enum TypeError {
+    /// A value of type `found` where one of type `expected` is needed.
+    Mismatch {
+        expected: Types,
+        found: Types,
+    },
+    /// The operand of the prefix operator `op` is not of type `expected`.
+    Operand {
+        op: &'static str,
+        expected: Types,
+        found: Types,
+    },
+    /// The operands of the binary operator `op` are not both `expected`.
+    Operands {
+        op: &'static str,
+        expected: &'static str,
+        left: Types,
+        right: Types,
+    },
+    /// Two values of this type cannot be compared with `==` or `!=`.
+    Incomparable(Types),
+    Unknown(String),
+    UnknownType(String),
+    NotAType(String),
+    /// The name of a type used as a value.
+    TypeAsValue(String, Types),
+    NotAFunction(String),
+    Arity {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A builtin, or `match`, given a value it cannot work on.
+    Expects {
+        name: String,
+        expected: &'static str,
+        found: Types,
+    },
+    CannotIndex(String, Types),
+    CannotDeref(String, Types),
+    /// `.field` on a value of a type without fields.
+    NoFields(String, Types),
+    NoField {
+        owner: String,
+        field: String,
+    },
+    NoVariant {
+        owner: String,
+        variant: String,
+    },
+    VecElement(Types),
+    ArrayElement(Types),
+}
+
+impl TypeError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            TypeError::Mismatch { .. }
+            | TypeError::Operand { .. }
+            | TypeError::Operands { .. }
+            | TypeError::Expects { .. } => ErrorCode::Mismatch,
+            TypeError::Incomparable(_) => ErrorCode::Incomparable,
+            TypeError::Unknown(_) => ErrorCode::UnknownName,
+            TypeError::UnknownType(_) | TypeError::NotAType(_) => ErrorCode::UnknownType,
+            TypeError::TypeAsValue(..) => ErrorCode::NotAValue,
+            TypeError::NotAFunction(_) => ErrorCode::NotAFunction,
+            TypeError::Arity { .. } => ErrorCode::Arity,
+            TypeError::CannotIndex(..) => ErrorCode::Index,
+            TypeError::CannotDeref(..) => ErrorCode::Deref,
+            TypeError::NoFields(..) | TypeError::NoField { .. } | TypeError::NoVariant { .. } => {
+                ErrorCode::NoMember
+            }
+            TypeError::VecElement(_) | TypeError::ArrayElement(_) => ErrorCode::Element,
+        }
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeError::Mismatch { expected, found } => {
+                write!(f, "Mismatched types, expected {expected} but found {found}")
+            }
+            TypeError::Operand {
+                op,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Mismatched types for '{op}', expected {expected} but found {found}"
+            ),
+            TypeError::Operands {
+                op,
+                expected,
+                left,
+                right,
+            } => write!(
+                f,
+                "Mismatched types for '{op}', expected {expected} on both sides but found {left} and {right}"
+            ),
+            TypeError::Incomparable(i_type @ (Types::Vec(_) | Types::Array(..))) => {
+                write!(f, "Vecs and arrays cannot be compared, found {i_type}")
+            }
+            TypeError::Incomparable(i_type @ Types::Enum(_)) => write!(
+                f,
+                "Enums cannot be compared, found {i_type}. Use match instead"
+            ),
+            TypeError::Incomparable(i_type) => write!(
+                f,
+                "Structs cannot be compared, found {i_type}. Compare their fields instead"
+            ),
+            TypeError::Unknown(name) => write!(f, "No identifier {name} found in current scope"),
+            TypeError::UnknownType(name) => write!(f, "No type {name} found in current scope"),
+            TypeError::NotAType(name) => write!(f, "{name} is not a type"),
+            TypeError::TypeAsValue(name, Types::Enum(_)) => write!(
+                f,
+                "{name} is a type, not a value. Create a {name} with {name}.Variant"
+            ),
+            TypeError::TypeAsValue(name, _) => write!(
+                f,
+                "{name} is a type, not a value. Create a {name} with {name} {{ ... }}"
+            ),
+            TypeError::NotAFunction(name) => write!(f, "{name} is not a function"),
+            TypeError::Arity {
+                name,
+                expected,
+                found,
+            } => {
+                let plural = if *expected == 1 { "" } else { "s" };
+                write!(
+                    f,
+                    "{name} takes {expected} argument{plural}, but was given {found}"
+                )
+            }
+            TypeError::Expects {
+                name,
+                expected,
+                found,
+            } => write!(f, "{name} expects {expected}, found {found}"),
+            TypeError::CannotIndex(name, i_type) => {
+                write!(f, "Cannot index {name} of type {i_type}")
+            }
+            TypeError::CannotDeref(name, i_type) => {
+                write!(f, "Cannot dereference {name} of type {i_type}")
+            }
+            TypeError::NoFields(field, i_type) => {
+                write!(f, "Cannot read field {field} of a value of type {i_type}")
+            }
+            TypeError::NoField { owner, field } => write!(f, "{owner} has no field {field}"),
+            TypeError::NoVariant { owner, variant } => {
+                write!(f, "{owner} has no variant {variant}")
+            }
+            TypeError::VecElement(found) => {
+                write!(f, "Vec elements must be Num, Text or Bool, found {found}")
+            }
+            TypeError::ArrayElement(found) => {
+                write!(f, "Array elements must be Num or Bool, found {found}")
+            }
+        }
+    }
+}
+
+/// Stops checking at `error`, found at `span`.
+fn fail<T>(span: &Span, error: TypeError) -> Result<T, Diagnostic> {
+    Err(Diagnostic::error(error.code(), span, error))
+}
+
+/// Stops checking at a problem found at `span` that is not about the type of a value.
+fn reject<T>(code: ErrorCode, span: &Span, message: impl fmt::Display) -> Result<T, Diagnostic> {
+    Err(Diagnostic::error(code, span, message))
+}
+
+/// The error for declaring `name` at `span` in a scope that declares it at `earlier` already.
+fn duplicate(name: &String, span: &Span, earlier: &Span) -> Diagnostic {
+    Diagnostic::error(
+        ErrorCode::Duplicate,
+        span,
+        format!("{name} is already declared in this scope"),
+    )
+    .with_label(earlier, format!("{name} is first declared here"))
+}
+
+/// `value` as a Num, which its type was checked to be.
+fn as_number(value: Value) -> Result<Number, Diagnostic> {
+    Number::from_value(value)
+        .ok_or_else(|| Diagnostic::internal("A value checked to be a Num is not one"))
+}
+
+/// `value` as a Bool, which its type was checked to be.
+fn as_bool(value: Value) -> Result<Bool, Diagnostic> {
+    Bool::from_value(value)
+        .ok_or_else(|| Diagnostic::internal("A value checked to be a Bool is not one"))
+}
+
+/// `value` passed as an argument to a call.
+fn argument(value: Value) -> Result<IdentifierNode, Diagnostic> {
+    IdentifierNode::from(value).ok_or_else(|| Diagnostic::internal("An argument has no type"))
+}
+
+/// Functions and constants every program can use. They have the default span, as they are not
+/// declared anywhere.
+fn builtins() -> SymbolTable {
+    let mut table = SymbolTable::new();
+    let showme = Function {
+        name: String::from("showme"),
+        body: BlockNode {
+            children: Vec::new(),
+            span: Span::default(),
+        },
+        ret: Types::Nothing,
+        params: Vec::new(),
+        span: Span::default(),
+    };
+    let showmeident = IdentifierNode {
+        name: String::from("showme"),
+        i_type: Types::Function(showme.signature()),
+        value: Some(Box::new(Value::Func(showme))),
+        mutable: false,
+        declared: Span::default(),
+        span: Span::default(),
+    };
+    table.insert(String::from("showme"), showmeident);
+
+    for (name, val) in [("EXIT_FAILURE", 1), ("EXIT_SUCCESS", 0)] {
+        let ident = IdentifierNode {
+            name: String::from(name),
+            value: Some(Box::from(Value::Lit(Literal::Num(NumLiteral {
+                val,
+                span: Span::default(),
+            })))),
+            i_type: Types::Number,
+            mutable: false,
+            declared: Span::default(),
+            span: Span::default(),
+        };
+        table.insert(String::from(name), ident);
+    }
+
+    // Text and Vec functions come from robtext.h and robvec.h, so they carry the name of
+    // their C counterpart
+    let textfuncs = [
+        (
+            "length",
+            "rob_text_len",
+            vec![param("text", Types::String)],
+            Types::Number,
+        ),
+        (
+            "slice",
+            "rob_text_slice",
+            vec![
+                param("text", Types::String),
+                param("start", Types::Number),
+                param("end", Types::Number),
+            ],
+            Types::String,
+        ),
+        (
+            "find",
+            "rob_text_find",
+            vec![param("text", Types::String), param("needle", Types::String)],
+            Types::Number,
+        ),
+        // Work on a Vec of any element type, see vec_call
+        ("push", "rob_vec_push", Vec::new(), Types::Nothing),
+        ("pop", "rob_vec_pop", Vec::new(), Types::Number),
+        // Also take a function to call on every element, see callback_call
+        (
+            "map",
+            "rob_vec_map",
+            Vec::new(),
+            Types::Vec(Box::new(Types::Nothing)),
+        ),
+        (
+            "filter",
+            "rob_vec_filter",
+            Vec::new(),
+            Types::Vec(Box::new(Types::Nothing)),
+        ),
+    ];
+    for (name, cname, params, ret) in textfuncs {
+        let func = Function {
+            name: String::from(cname),
+            params,
+            ret,
+            body: BlockNode {
+                children: Vec::new(),
+                span: Span::default(),
+            },
+            span: Span::default(),
+        };
+        let i_type = Types::Function(func.signature());
+        let ident = IdentifierNode::new(&String::from(name), &i_type, Value::Func(func));
+        table.insert(String::from(name), ident);
+    }
+    table
+}
+
+/// A parameter of a builtin.
+fn param(name: &str, i_type: Types) -> IdentifierNode {
+    IdentifierNode {
+        name: String::from(name),
+        i_type,
+        value: None,
+        mutable: false,
+        declared: Span::default(),
+        span: Span::default(),
+    }
+}
+
+impl BinaryOp {
+    fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Num(Operators::Plus) => "+",
+            BinaryOp::Num(Operators::Minus) => "-",
+            BinaryOp::Num(Operators::Mul) => "*",
+            BinaryOp::Num(Operators::Div) => "/",
+            BinaryOp::Num(Operators::Mod) => "%",
+            BinaryOp::Bool(BoolOps::EQ) => "==",
+            BinaryOp::Bool(BoolOps::NotEq) => "!=",
+            BinaryOp::Bool(BoolOps::Greater) => ">",
+            BinaryOp::Bool(BoolOps::Lesser) => "<",
+            BinaryOp::Bool(BoolOps::GreaterEq) => ">=",
+            BinaryOp::Bool(BoolOps::LesserEq) => "<=",
+            BinaryOp::Bool(BoolOps::And) => "&&",
+            BinaryOp::Bool(BoolOps::Or) => "||",
+        }
+    }
+}
+
+impl TypeChecker {
+    fn new() -> Self {
+        TypeChecker {
+            symbols: SymbolStack::new(),
+            loops: Vec::new(),
+            label_count: 0,
+            match_depth: 0,
+            match_count: 0,
+            func_types: Vec::new(),
+            open_lambdas: Vec::new(),
+            lambdas: Vec::new(),
+            pending_types: HashMap::new(),
+            defining: Vec::new(),
+        }
+    }
+
+    /// Checks the top level of the program, which may only declare types, functions and
+    /// constants, one of which is the function main.
+    fn program(&mut self, program: &[Stmt]) -> Result<Vec<StatementNode>, Diagnostic> {
+        self.symbols.push(builtins());
+        self.collect(program)?;
+        let mut children = Vec::new();
+        for stmt in program {
+            let stmt = match stmt {
+                Stmt::Let(stmt) => stmt,
+                Stmt::Return(node) => {
+                    return reject(
+                        ErrorCode::Placement,
+                        &node.span,
+                        "Cannot return from global scope",
+                    )
+                }
+                other => {
+                    return reject(
+                        ErrorCode::Unsupported,
+                        other.span(),
+                        "Top level code coming soon",
+                    )
+                }
+            };
+            let node = match &stmt.kind {
+                LetKind::Func(func) => {
+                    let Some(Value::Func(signature)) = self
+                        .global(&stmt.name)
+                        .and_then(|ident| ident.value)
+                        .map(|v| *v)
+                    else {
+                        return Err(Diagnostic::internal(format!(
+                            "Function {} should have been collected already",
+                            stmt.name
+                        )));
+                    };
+                    let func = self.function(&stmt.name, signature.params, signature.ret, func)?;
+                    let i_type = Types::Function(func.signature());
+                    let mut ident = IdentifierNode::new(&stmt.name, &i_type, Value::Func(func));
+                    ident.declare_at(&stmt.span);
+                    self.declare(ident.clone());
+                    DecAssignNode {
+                        ident,
+                        i_type,
+                        span: stmt.span.clone(),
+                    }
+                }
+                // Types and constants were done while collecting
+                LetKind::Type(_) | LetKind::Value(..) => {
+                    let ident = self.global(&stmt.name).ok_or_else(|| {
+                        Diagnostic::internal(format!(
+                            "{} should have been collected already",
+                            stmt.name
+                        ))
+                    })?;
+                    let i_type = ident.i_type.clone();
+                    DecAssignNode {
+                        ident,
+                        i_type,
+                        span: stmt.span.clone(),
+                    }
+                }
+                LetKind::Declare(_) => {
+                    return reject(
+                        ErrorCode::Unsupported,
+                        &stmt.span,
+                        "Top level code coming soon",
+                    )
+                }
+            };
+            children.push(StatementNode::DeclareAssign(node));
+        }
+        // The program starts at main, which has to be a function
+        let main = self
+            .global(&String::from("main"))
+            .and_then(|ident| ident.value);
+        if !matches!(main.as_deref(), Some(Value::Func(_))) {
+            return Err(Diagnostic::global(
+                ErrorCode::NoMain,
+                "No main function found",
+            ));
+        }
+        Ok(children)
+    }
+
+    fn global(&self, name: &String) -> Option<IdentifierNode> {
+        self.symbols.current()?.get(name).cloned()
+    }
+
+    /// First passes over the top level of the program. Collects every type declared there, then
+    /// the signature of every function and then every constant, so that types, signatures and
+    /// calls can use types and functions declared further down, and functions can use constants
+    /// declared further down. Constants can only use constants declared before them.
+    fn collect(&mut self, program: &[Stmt]) -> Result<(), Diagnostic> {
+        let lets: Vec<&LetStmt> = program
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Let(stmt) => Some(stmt),
+                _ => None,
+            })
+            .collect();
+        for stmt in &lets {
+            let LetKind::Type(definition) = &stmt.kind else {
+                continue;
+            };
+            if stmt.mutable {
+                return reject(
+                    ErrorCode::Immutable,
+                    &stmt.span,
+                    "Types cannot be declared mutable",
+                );
+            }
+            self.unique(stmt)?;
+            if let Some((_, earlier)) = self.pending_types.get(&stmt.name) {
+                return Err(duplicate(&stmt.name, &stmt.span, earlier));
+            }
+            let declared = (definition.clone(), stmt.span.clone());
+            self.pending_types.insert(stmt.name.clone(), declared);
+        }
+        for stmt in &lets {
+            if matches!(stmt.kind, LetKind::Type(_)) {
+                self.define_type(&stmt.name)?;
+            }
+        }
+        for stmt in &lets {
+            let LetKind::Func(func) = &stmt.kind else {
+                continue;
+            };
+            if stmt.mutable {
+                return reject(
+                    ErrorCode::Immutable,
+                    &stmt.span,
+                    "Functions cannot be declared mutable",
+                );
+            }
+            self.unique(stmt)?;
+            let (params, ret) = self.signature(func)?;
+            let func = Function {
+                name: stmt.name.clone(),
+                params,
+                ret,
+                body: BlockNode {
+                    children: Vec::new(),
+                    span: func.span.clone(),
+                },
+                span: func.span.clone(),
+            };
+            let i_type = Types::Function(func.signature());
+            let mut ident = IdentifierNode::new(&stmt.name, &i_type, Value::Func(func));
+            ident.declare_at(&stmt.span);
+            self.declare(ident);
+        }
+        for stmt in &lets {
+            let LetKind::Value(annotation, value) = &stmt.kind else {
+                continue;
+            };
+            self.unique(stmt)?;
+            let (value, i_type) = self.let_value(&stmt.name, annotation.as_ref(), value)?;
+            let value =
+                Self::constant_value(&stmt.name, &i_type, &value, stmt.mutable, &stmt.span)?;
+            let mut ident = IdentifierNode::new(&stmt.name, &i_type, value);
+            ident.declare_at(&stmt.span);
+            self.declare(ident);
+        }
+        Ok(())
+    }
+
+    /// Rejects a second top level declaration of the same name. Builtins may be declared again.
+    fn unique(&self, stmt: &LetStmt) -> Result<(), Diagnostic> {
+        if let Some(earlier) = self.global(&stmt.name) {
+            if earlier.declared.is_builtin() {
+                return Ok(());
+            }
+        }
+        self.unique_local(&stmt.name, &stmt.span)
+    }
+
+    /// Rejects a second declaration of `name` in the scope being checked, which C does not
+    /// allow either. Inner scopes may shadow the names of outer ones.
+    fn unique_local(&self, name: &String, span: &Span) -> Result<(), Diagnostic> {
+        let Some(earlier) = self.symbols.current().and_then(|table| table.get(name)) else {
+            return Ok(());
+        };
+        Err(duplicate(name, span, &earlier.declared))
+    }
+
+    fn declare(&mut self, ident: IdentifierNode) {
+        if let Some(table) = self.symbols.current_mut() {
+            table.insert(ident.name.clone(), ident);
+        }
+    }
+
+    /// The literal a top level constant holds.
+    fn constant_value(
+        name: &String,
+        i_type: &Types,
+        value: &Value,
+        mutable: bool,
+        span: &Span,
+    ) -> Result<Value, Diagnostic> {
+        if mutable {
+            return reject(
+                ErrorCode::Unsupported,
+                span,
+                format!("Top level variables are coming soon, declare {name} without mut to make it a constant"),
+            );
+        }
+        let folded = match i_type {
+            Types::Number | Types::Bool | Types::String => constant::evaluate(value),
+            Types::Array(element, _) if matches!(**element, Types::Number | Types::Bool) => {
+                match constant::array(value) {
+                    Ok(lit) => return Ok(Value::Array(lit)),
+                    Err(folded) => folded,
+                }
+            }
+            _ => {
+                return reject(
+                    ErrorCode::Unsupported,
+                    span,
+                    format!("Top level constants of type {i_type} are coming soon"),
+                )
+            }
+        };
+        match folded {
+            Folded::Const(lit) => Ok(Value::Lit(lit)),
+            Folded::Runtime(part) => reject(
+                ErrorCode::Constant,
+                span,
+                format!("The value of the top level constant {name} has to be known at compile time, but it uses {part}"),
+            ),
+            Folded::Invalid(message) => reject(ErrorCode::Constant, span, message),
+        }
+    }
+
+    /// Defines the top level type `name`, unless it is defined already.
+    fn define_type(&mut self, name: &String) -> Result<(), Diagnostic> {
+        let Some((definition, span)) = self.pending_types.remove(name) else {
+            return Ok(());
+        };
+        self.defining.push(name.clone());
+        let defined = self.type_definition(name, &definition, &span)?;
+        self.defining.pop();
+        let mut ident = IdentifierNode::new(name, &Types::Type, Value::Type(defined));
+        ident.declare_at(&span);
+        self.declare(ident);
+        Ok(())
+    }
+
+    /// The struct or enum type called `name` declared by `definition`.
+    fn type_definition(
+        &mut self,
+        name: &String,
+        definition: &TypeDef,
+        span: &Span,
+    ) -> Result<Types, Diagnostic> {
+        match definition {
+            TypeDef::Struct(fields) => {
+                let fields = self.fields(&format!("struct {name}"), fields)?;
+                if fields.is_empty() {
+                    return reject(
+                        ErrorCode::NoMember,
+                        span,
+                        format!("Struct {name} needs at least one field"),
+                    );
+                }
+                Ok(Types::Struct(StructType {
+                    name: name.clone(),
+                    fields,
+                }))
+            }
+            TypeDef::Enum(declared) => {
+                let mut variants: Vec<StructType> = Vec::new();
+                for variant in declared {
+                    if variants.iter().any(|earlier| earlier.name == variant.name) {
+                        return reject(
+                            ErrorCode::Duplicate,
+                            &variant.span,
+                            format!("Enum {name} has more than one variant {}", variant.name),
+                        );
+                    }
+                    let fields =
+                        self.fields(&format!("{name}.{}", variant.name), &variant.fields)?;
+                    variants.push(StructType {
+                        name: variant.name.clone(),
+                        fields,
+                    });
+                }
+                if variants.is_empty() {
+                    return reject(
+                        ErrorCode::NoMember,
+                        span,
+                        format!("Enum {name} needs at least one variant"),
+                    );
+                }
+                Ok(Types::Enum(EnumType {
+                    name: name.clone(),
+                    variants,
+                }))
+            }
+        }
+    }
+
+    /// The fields of the struct or enum variant called `owner`.
+    fn fields(
+        &mut self,
+        owner: &String,
+        declared: &[FieldDecl],
+    ) -> Result<Vec<(String, Types)>, Diagnostic> {
+        let mut fields: Vec<(String, Types)> = Vec::new();
+        for field in declared {
+            if fields.iter().any(|(earlier, _)| *earlier == field.name) {
+                return reject(
+                    ErrorCode::Duplicate,
+                    &field.span,
+                    format!("{owner} has more than one field {}", field.name),
+                );
+            }
+            let i_type = self.resolve(&field.i_type)?;
+            match i_type {
+                Types::String | Types::Vec(_) => {
+                    return reject(
+                        ErrorCode::Unsupported,
+                        &field.i_type.span,
+                        "Text and Vec fields are coming soon",
+                    );
+                }
+                Types::Function(_) => {
+                    return reject(
+                        ErrorCode::Unsupported,
+                        &field.i_type.span,
+                        "Function fields are coming soon",
+                    );
+                }
+                _ => {}
+            }
+            fields.push((field.name.clone(), i_type));
+        }
+        Ok(fields)
+    }
+
+    /// The type `i_type` stands for. Function types are remembered so they get a C typedef.
+    fn resolve(&mut self, i_type: &TypeExpr) -> Result<Types, Diagnostic> {
+        let resolved = match &i_type.kind {
+            TypeKind::Number => Types::Number,
+            TypeKind::String => Types::String,
+            TypeKind::Bool => Types::Bool,
+            TypeKind::Nothing => Types::Nothing,
+            TypeKind::Vec(element) => {
+                let found = self.resolve(element)?;
+                if !matches!(found, Types::Number | Types::String | Types::Bool) {
+                    return fail(&element.span, TypeError::VecElement(found));
+                }
+                Types::Vec(Box::new(found))
+            }
+            TypeKind::Array(element, len) => {
+                let found = self.resolve(element)?;
+                if !matches!(found, Types::Number | Types::Bool) {
+                    return fail(&element.span, TypeError::ArrayElement(found));
+                }
+                Types::Array(Box::new(found), *len)
+            }
+            TypeKind::Pointer(inner) => Types::Pointer(Box::new(self.resolve(inner)?)),
+            TypeKind::MutPointer(inner) => Types::MutPointer(Box::new(self.resolve(inner)?)),
+            TypeKind::Function(params, ret) => {
+                let params = params
+                    .iter()
+                    .map(|param| self.resolve(param))
+                    .collect::<Result<Vec<Types>, Diagnostic>>()?;
+                let signature = FuncType {
+                    params,
+                    ret: Box::new(self.return_type(ret)?),
+                };
+                self.note_func_type(&signature);
+                Types::Function(signature)
+            }
+            TypeKind::Named(name) => {
+                if self.defining.contains(name) {
+                    return reject(
+                        ErrorCode::UnknownType,
+                        &i_type.span,
+                        format!("{name} cannot be used in its own definition"),
+                    );
+                }
+                self.define_type(name)?;
+                let Some(found) = self.symbols.search_down(name) else {
+                    return fail(&i_type.span, TypeError::UnknownType(name.clone()));
+                };
+                let Some(Value::Type(found)) = found.value.map(|value| *value) else {
+                    return fail(&i_type.span, TypeError::NotAType(name.clone()));
+                };
+                found
+            }
+        };
+        Ok(resolved)
+    }
+
+    fn return_type(&mut self, ret: &TypeExpr) -> Result<Types, Diagnostic> {
+        match self.resolve(ret)? {
+            Types::Array(..) => reject(
+                ErrorCode::Return,
+                &ret.span,
+                "Functions cannot return arrays, return a Vec instead",
+            ),
+            ret => Ok(ret),
+        }
+    }
+
+    /// The parameters and return type of a function.
+    fn signature(&mut self, func: &FuncExpr) -> Result<(Vec<IdentifierNode>, Types), Diagnostic> {
+        let mut params: Vec<IdentifierNode> = Vec::new();
+        for param in &func.params {
+            if params.iter().any(|earlier| earlier.name == param.name) {
+                return reject(
+                    ErrorCode::Duplicate,
+                    &param.span,
+                    format!("Parameter {} is declared more than once", param.name),
+                );
+            }
+            params.push(IdentifierNode {
+                name: param.name.clone(),
+                i_type: self.resolve(&param.i_type)?,
+                value: None,
+                mutable: false,
+                declared: param.span.clone(),
+                span: param.span.clone(),
+            });
+        }
+        Ok((params, self.return_type(&func.ret)?))
+    }
+
+    /// Records a function type used by the program, so it gets its C typedef.
+    fn note_func_type(&mut self, signature: &FuncType) {
+        if !self.func_types.contains(signature) {
+            self.func_types.push(signature.clone());
+        }
+    }
+
+    /// Checks the body of the function `func`, whose parameters and return type are resolved
+    /// already.
+    fn function(
+        &mut self,
+        name: &String,
+        params: Vec<IdentifierNode>,
+        ret: Types,
+        func: &FuncExpr,
+    ) -> Result<Function, Diagnostic> {
+        // Loops around a nested function definition cannot be broken out of from inside it
+        let outerloops = std::mem::take(&mut self.loops);
+        let outermatches = std::mem::take(&mut self.match_depth);
+        let body = self.block(&func.body, params.clone(), &ret, &func.span);
+        self.loops = outerloops;
+        self.match_depth = outermatches;
+        Ok(Function {
+            name: name.clone(),
+            params,
+            ret,
+            body: body?,
+            span: func.span.clone(),
+        })
+    }
+
+    /// Checks a block in its own scope, which starts out holding `params`. `ret` is the return
+    /// type of the function the block is in, and `owner` the span of what the block belongs to.
+    fn block(
+        &mut self,
+        stmts: &[Stmt],
+        params: Vec<IdentifierNode>,
+        ret: &Types,
+        owner: &Span,
+    ) -> Result<BlockNode, Diagnostic> {
+        let mut table = SymbolTable::new();
+        for param in params {
+            table.insert(param.name.clone(), param);
+        }
+        self.symbols.push(table);
+        let mut children = Vec::new();
+        for stmt in stmts {
+            match self.statement(stmt, ret) {
+                Ok(child) => children.push(child),
+                Err(diagnostic) => {
+                    self.symbols.pop();
+                    return Err(diagnostic);
+                }
+            }
+        }
+        self.symbols.pop();
+        let span = match (stmts.first(), stmts.last()) {
+            (Some(first), Some(last)) => first.span().to(last.span()),
+            _ => owner.clone(),
+        };
+        Ok(BlockNode { children, span })
+    }
+
+    fn statement(&mut self, stmt: &Stmt, ret: &Types) -> Result<StatementNode, Diagnostic> {
+        let node = match stmt {
+            Stmt::Let(stmt) => self.local_let(stmt)?,
+            Stmt::Assign(stmt) => StatementNode::Assign(self.assign(stmt)?),
+            Stmt::Call(call) => StatementNode::Call(self.call(call)?),
+            Stmt::If(stmt) => StatementNode::Conditional(self.conditional(stmt, ret)?),
+            Stmt::Return(stmt) => StatementNode::Return(self.return_value(stmt, ret)?),
+            Stmt::Inline(stmt) => StatementNode::Inline(InlineC {
+                code: stmt.code.clone(),
+                span: stmt.span.clone(),
+            }),
+            Stmt::For(stmt) => StatementNode::ForLoop(self.for_loop(stmt, ret)?),
+            Stmt::While(stmt) => StatementNode::WhileLoop(self.while_loop(stmt, ret)?),
+            Stmt::Break(stmt) => StatementNode::Break(self.loop_control("break", stmt)?),
+            Stmt::Continue(stmt) => StatementNode::Continue(self.loop_control("continue", stmt)?),
+            Stmt::Match(stmt) => StatementNode::Match(self.match_stmt(stmt, ret)?),
+        };
+        Ok(node)
+    }
+
+    /// Checks a `let` inside a function, which declares a binding or a nested function.
+    fn local_let(&mut self, stmt: &LetStmt) -> Result<StatementNode, Diagnostic> {
+        let (name, span) = (&stmt.name, &stmt.span);
+        match &stmt.kind {
+            LetKind::Type(_) => reject(
+                ErrorCode::Placement,
+                span,
+                "Types can only be declared at the top level",
+            ),
+            LetKind::Func(func) => {
+                if stmt.mutable {
+                    return reject(
+                        ErrorCode::Immutable,
+                        span,
+                        "Functions cannot be declared mutable",
+                    );
+                }
+                self.unique_local(name, span)?;
+                // C has no nested functions, so it is lifted out like a lambda bound to `name`
+                let (value, i_type) = self.lambda(func, span)?;
+                let mut ident = IdentifierNode::new(name, &i_type, value);
+                ident.declare_at(span);
+                self.declare(ident.clone());
+                Ok(StatementNode::DeclareAssign(DecAssignNode {
+                    ident,
+                    i_type,
+                    span: span.clone(),
+                }))
+            }
+            // Bindings declared without a value are assigned later, and
+            // `analysis::assignment` checks that immutable ones are only ever assigned once
+            LetKind::Declare(i_type) => {
+                self.unique_local(name, span)?;
+                let i_type = self.resolve(i_type)?;
+                let ident = IdentifierNode {
+                    name: name.clone(),
+                    i_type: i_type.clone(),
+                    value: None,
+                    mutable: stmt.mutable,
+                    declared: span.clone(),
+                    span: span.clone(),
+                };
+                self.declare(ident.clone());
+                Ok(StatementNode::Declare(DeclareNode {
+                    ident,
+                    i_type,
+                    span: span.clone(),
+                }))
+            }
+            LetKind::Value(annotation, value) => {
+                self.unique_local(name, span)?;
+                let (value, i_type) = self.let_value(name, annotation.as_ref(), value)?;
+                let mut ident = IdentifierNode::new(name, &i_type, value);
+                ident.mutable = stmt.mutable;
+                ident.declare_at(span);
+                self.declare(ident.clone());
+                Ok(StatementNode::DeclareAssign(DecAssignNode {
+                    ident,
+                    i_type,
+                    span: span.clone(),
+                }))
+            }
+        }
+    }
+
+    /// The value of `let name: annotation = value;` and the type `name` gets, which is the
+    /// type of the value when there is no annotation.
+    fn let_value(
+        &mut self,
+        name: &String,
+        annotation: Option<&TypeExpr>,
+        value: &Expr,
+    ) -> Result<(Value, Types), Diagnostic> {
+        if let Some(annotation) = annotation {
+            let i_type = self.resolve(annotation)?;
+            let value = self.value(&i_type, value)?;
+            return Ok((value, i_type));
+        }
+        let (found, i_type) = self.expression(value)?;
+        if i_type.element() == Some(&Types::Nothing) {
+            return reject(
+                ErrorCode::Element,
+                &value.span,
+                format!("Cannot infer the type of {name} from an empty Vec. Give it a type, like let {name}: Vec[Num] = [];"),
+            );
+        }
+        Ok((found, i_type))
+    }
+
+    /// Finds the binding of `name`. A local variable of a function around the lambda being
+    /// checked is captured by it, and by every lambda in between.
+    fn lookup(&mut self, name: &String) -> Option<IdentifierNode> {
+        let (depth, ident) = self.symbols.search_depth(name)?;
+        if depth == 0 {
+            return Some(ident);
+        }
+        for scope in self.open_lambdas.iter_mut().rev() {
+            if depth >= scope.base {
+                break;
+            }
+            if !scope.captures.iter().any(|capture| capture.name == *name) {
+                scope.captures.push(ident.clone());
+            }
+        }
+        Some(ident)
+    }
+
+    /// Like `lookup`, reporting a name that is not declared. The binding is returned with the
+    /// span of its use at `span`.
+    fn find(&mut self, name: &String, span: &Span) -> Result<IdentifierNode, Diagnostic> {
+        match self.lookup(name) {
+            Some(ident) => Ok(IdentifierNode {
+                span: span.clone(),
+                ..ident
+            }),
+            None => fail(span, TypeError::Unknown(name.clone())),
+        }
+    }
+
+    /// Checks `name = value;` and the other assignments. Bindings declared without a value are
+    /// let through here, and `analysis::assignment` checks that immutable ones are only ever
+    /// assigned once.
+    fn assign(&mut self, stmt: &AssignStmt) -> Result<AssignNode, Diagnostic> {
+        let span = &stmt.span;
+        let name = match &stmt.target {
+            Place::Name(name)
+            | Place::Deref(name)
+            | Place::Index(name, _)
+            | Place::Field(name, _) => name,
+        };
+        let target = self.find(name, span)?;
+        let mut node = AssignNode {
+            ident: target.clone(),
+            deref: false,
+            index: None,
+            fields: Vec::new(),
+            span: span.clone(),
+        };
+        let value_type = match &stmt.target {
+            Place::Name(_) => {
+                if !target.mutable && target.value.is_some() {
+                    return Err(assigned_twice(name, span, &target.declared));
+                }
+                target.i_type.clone()
+            }
+            Place::Deref(_) => {
+                node.deref = true;
+                match &target.i_type {
+                    Types::MutPointer(inner) => (**inner).clone(),
+                    Types::Pointer(_) => {
+                        return reject(
+                            ErrorCode::Immutable,
+                            span,
+                            format!("Cannot write through {name}, it points to an immutable value"),
+                        );
+                    }
+                    other => {
+                        return fail(span, TypeError::CannotDeref(name.clone(), other.clone()))
+                    }
+                }
+            }
+            Place::Index(_, index) => {
+                let indexed = target.i_type.indexed().clone();
+                let Some(element) = indexed.element().cloned() else {
+                    return fail(
+                        span,
+                        TypeError::CannotIndex(name.clone(), target.i_type.clone()),
+                    );
+                };
+                match &target.i_type {
+                    Types::MutPointer(_) => {}
+                    Types::Pointer(_) => {
+                        return reject(
+                            ErrorCode::Immutable,
+                            span,
+                            format!("Cannot write through {name}, it points to an immutable value"),
+                        );
+                    }
+                    _ if !target.mutable => {
+                        return Err(Diagnostic::error(
+                            ErrorCode::Immutable,
+                            span,
+                            format!("Cannot assign to an element of immutable variable {name}"),
+                        )
+                        .with_label(
+                            &target.declared,
+                            format!("{name} is declared here. Declare it with 'let mut' to make it changeable"),
+                        ));
+                    }
+                    _ => {}
+                }
+                node.index = Some(self.subscript(&indexed, index)?);
+                element
+            }
+            Place::Field(_, fields) => {
+                let (field_type, writable) = self.field_path(&target, fields, span)?;
+                if !writable {
+                    if target.i_type.pointee().is_some() {
+                        return reject(
+                            ErrorCode::Immutable,
+                            span,
+                            format!("Cannot write through {name}, it points to an immutable value"),
+                        );
+                    }
+                    return Err(Diagnostic::error(
+                        ErrorCode::Immutable,
+                        span,
+                        format!("Cannot assign to a field of immutable variable {name}"),
+                    )
+                    .with_label(
+                        &target.declared,
+                        format!("{name} is declared here. Declare it with 'let mut' to make it changeable"),
+                    ));
+                }
+                node.fields = fields.clone();
+                field_type
+            }
+        };
+        let value = self.value(&value_type, &stmt.value)?;
+        node.ident.value = Some(Box::new(value));
+        Ok(node)
+    }
+
+    /// Follows `fields` from `target`. Returns the type of the last field and whether it can be
+    /// written, which takes a mutable binding or a `*mut` pointer for the last pointer passed on
+    /// the way.
+    fn field_path(
+        &mut self,
+        target: &IdentifierNode,
+        fields: &[String],
+        span: &Span,
+    ) -> Result<(Types, bool), Diagnostic> {
+        let mut current = target.i_type.clone();
+        let mut writable = target.mutable;
+        for name in fields {
+            let Some(structure) = current.structure() else {
+                return fail(span, TypeError::NoFields(name.clone(), current));
+            };
+            let Some(field_type) = structure.field(name).cloned() else {
+                return fail(
+                    span,
+                    TypeError::NoField {
+                        owner: structure.name.clone(),
+                        field: name.clone(),
+                    },
+                );
+            };
+            match current {
+                Types::Pointer(_) => writable = false,
+                Types::MutPointer(_) => writable = true,
+                _ => {}
+            }
+            current = field_type;
+        }
+        Ok((current, writable))
+    }
+
+    /// Checks that `expr` has the type `i_type`.
+    fn value(&mut self, i_type: &Types, expr: &Expr) -> Result<Value, Diagnostic> {
+        let (mut value, found) = self.expression(expr)?;
+        if !i_type.accepts(&found) {
+            return fail(
+                &expr.span,
+                TypeError::Mismatch {
+                    expected: i_type.clone(),
+                    found,
+                },
+            );
+        }
+        // An empty Vec literal takes its element type from where it is used
+        if let (Value::Vec(lit), Some(element)) = (&mut value, i_type.element()) {
+            lit.element = element.clone();
+        }
+        Ok(value)
+    }
+
+    fn number(&mut self, expr: &Expr) -> Result<Number, Diagnostic> {
+        as_number(self.value(&Types::Number, expr)?)
+    }
+
+    fn boolean(&mut self, expr: &Expr) -> Result<Bool, Diagnostic> {
+        as_bool(self.value(&Types::Bool, expr)?)
+    }
+
+    /// The typed value of `expr`, along with its type.
+    fn expression(&mut self, expr: &Expr) -> Result<(Value, Types), Diagnostic> {
+        let span = &expr.span;
+        match &expr.kind {
+            ExprKind::Num(val) => Ok((
+                Value::Lit(Literal::Num(NumLiteral {
+                    val: *val,
+                    span: span.clone(),
+                })),
+                Types::Number,
+            )),
+            ExprKind::Bool(val) => {
+                let lit = if *val {
+                    BoolLiteral::True
+                } else {
+                    BoolLiteral::False
+                };
+                Ok((Value::Lit(Literal::Bool(lit, span.clone())), Types::Bool))
+            }
+            ExprKind::Text(text) => Ok((
+                Value::Lit(Literal::Text(TextLit {
+                    value: text.clone(),
+                    span: span.clone(),
+                })),
+                Types::String,
+            )),
+            ExprKind::Neg(inner) => {
+                let (inner, found) = self.expression(inner)?;
+                if found != Types::Number {
+                    return fail(
+                        span,
+                        TypeError::Operand {
+                            op: "-",
+                            expected: Types::Number,
+                            found,
+                        },
+                    );
+                }
+                let negated = Number::Neg(Box::new(as_number(inner)?), span.clone());
+                Ok((negated.to_value(), Types::Number))
+            }
+            ExprKind::Not(inner) => {
+                let (inner, found) = self.expression(inner)?;
+                if found != Types::Bool {
+                    return fail(
+                        span,
+                        TypeError::Operand {
+                            op: "!",
+                            expected: Types::Bool,
+                            found,
+                        },
+                    );
+                }
+                let inner = as_bool(inner)?;
+                Ok((
+                    Bool::Not(Box::new(inner), span.clone()).to_value(),
+                    Types::Bool,
+                ))
+            }
+            ExprKind::Binary(op, left, right) => {
+                let (left, left_type) = self.expression(left)?;
+                let (right, right_type) = self.expression(right)?;
+                Self::combine(op, left, left_type, right, right_type, span)
+            }
+            ExprKind::Vec(elements) => self.vec_literal(elements, span),
+            ExprKind::Array(value, len) => {
+                let (value, element) = self.expression(value)?;
+                if !matches!(element, Types::Number | Types::Bool) {
+                    return fail(span, TypeError::ArrayElement(element));
+                }
+                let arraytype = Types::Array(Box::new(element.clone()), *len);
+                let lit = ArrayLiteral {
+                    value: Box::new(value),
+                    element,
+                    len: *len,
+                    span: span.clone(),
+                };
+                Ok((Value::Array(lit), arraytype))
+            }
+            ExprKind::Ident(name) => {
+                let ident = self.value_ident(name, span)?;
+                if let Types::Function(signature) = &ident.i_type {
+                    // Built in functions have no span, and some take arguments of any type
+                    if ident.declared.is_builtin() {
+                        return reject(
+                            ErrorCode::NotAValue,
+                            span,
+                            format!("{name} is built in, so it can only be called"),
+                        );
+                    }
+                    self.note_func_type(signature);
+                }
+                let i_type = ident.i_type.clone();
+                Ok((Value::Ident(ident), i_type))
+            }
+            ExprKind::Ref(name) => {
+                let ident = self.value_ident(name, span)?;
+                if matches!(ident.i_type, Types::Function(_)) {
+                    return reject(
+                        ErrorCode::NotAValue,
+                        span,
+                        format!("Cannot take a reference to function {name}"),
+                    );
+                }
+                let inner = Box::new(ident.i_type.clone());
+                let ptrtype = if ident.mutable {
+                    Types::MutPointer(inner)
+                } else {
+                    Types::Pointer(inner)
+                };
+                let value = Value::Pointer(Box::new(Value::Ident(ident)), span.clone());
+                Ok((value, ptrtype))
+            }
+            ExprKind::Deref(name) => {
+                let ident = self.value_ident(name, span)?;
+                let Some(pointee) = ident.i_type.pointee().cloned() else {
+                    return fail(span, TypeError::CannotDeref(name.clone(), ident.i_type));
+                };
+                Ok((Value::Deref(ident), pointee))
+            }
+            ExprKind::Field(name, fields) => {
+                let ident = self.find(name, span)?;
+                // `Name.Variant` creates a variant without fields
+                if let Some(Value::Type(Types::Enum(enumeration))) = ident.value.as_deref() {
+                    if let [variant] = fields.as_slice() {
+                        return self.variant(enumeration, variant, None, span);
+                    }
+                }
+                if let Some(Value::Type(i_type)) = ident.value.as_deref() {
+                    return fail(span, TypeError::TypeAsValue(name.clone(), i_type.clone()));
+                }
+                let (i_type, _) = self.field_path(&ident, fields, span)?;
+                let node = FieldNode {
+                    target: ident,
+                    fields: fields.clone(),
+                    i_type: i_type.clone(),
+                    span: span.clone(),
+                };
+                Ok((Value::Field(node), i_type))
+            }
+            ExprKind::Index(name, index) => {
+                let vec = self.value_ident(name, span)?;
+                let indexed = vec.i_type.indexed().clone();
+                let Some(element) = indexed.element().cloned() else {
+                    return fail(span, TypeError::CannotIndex(name.clone(), vec.i_type));
+                };
+                let index = self.subscript(&indexed, index)?;
+                let node = IndexNode {
+                    vec,
+                    index: Box::new(index),
+                    span: span.clone(),
+                };
+                Ok((Value::Index(node), element))
+            }
+            ExprKind::Call(call) => {
+                let node = self.call(call)?;
+                if node.func.ret == Types::Nothing {
+                    return reject(
+                        ErrorCode::NotAValue,
+                        span,
+                        format!(
+                            "{} returns Nothing, so its call cannot be used as a value",
+                            call.name
+                        ),
+                    );
+                }
+                let ret = node.func.ret.clone();
+                Ok((Value::Call(node), ret))
+            }
+            ExprKind::Literal(name, variant, fields) => {
+                let ident = self.find(name, span)?;
+                let Some(Value::Type(i_type)) = ident.value.as_deref() else {
+                    return fail(span, TypeError::NotAType(name.clone()));
+                };
+                match (i_type, variant) {
+                    (Types::Struct(structure), None) => {
+                        let fields = self.literal_fields(structure, name, fields, span)?;
+                        let lit = StructLiteral {
+                            structure: structure.clone(),
+                            fields,
+                            span: span.clone(),
+                        };
+                        Ok((Value::Struct(lit), i_type.clone()))
+                    }
+                    (Types::Enum(enumeration), Some(variant)) => {
+                        self.variant(enumeration, variant, Some(fields), span)
+                    }
+                    (i_type, _) => fail(span, TypeError::TypeAsValue(name.clone(), i_type.clone())),
+                }
+            }
+            ExprKind::Lambda(lambda) => self.lambda(lambda, span),
+        }
+    }
+
+    /// Finds the binding of `name` used as a value, which a type cannot be.
+    fn value_ident(&mut self, name: &String, span: &Span) -> Result<IdentifierNode, Diagnostic> {
+        let ident = self.find(name, span)?;
+        if let Some(Value::Type(i_type)) = ident.value.as_deref() {
+            return fail(span, TypeError::TypeAsValue(name.clone(), i_type.clone()));
+        }
+        Ok(ident)
+    }
+
+    /// Type checks a binary operation and builds its node.
+    fn combine(
+        op: &BinaryOp,
+        left: Value,
+        left_type: Types,
+        right: Value,
+        right_type: Types,
+        span: &Span,
+    ) -> Result<(Value, Types), Diagnostic> {
+        let operands = |expected| TypeError::Operands {
+            op: op.symbol(),
+            expected,
+            left: left_type.clone(),
+            right: right_type.clone(),
+        };
+        match op {
+            BinaryOp::Num(Operators::Plus)
+                if left_type == Types::String && right_type == Types::String =>
+            {
+                let expr = TextExpr {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    span: span.clone(),
+                };
+                Ok((Value::Expr(Expression::Text(expr)), Types::String))
+            }
+            BinaryOp::Num(operator) => {
+                if left_type != Types::Number || right_type != Types::Number {
+                    return fail(span, operands("Num"));
+                }
+                let expr =
+                    NumExpression::new(as_number(left)?, operator.clone(), as_number(right)?);
+                let expr = Number::Exp(Box::new(expr));
+                Ok((expr.to_value(), Types::Number))
+            }
+            BinaryOp::Bool(operator) => {
+                if operator.is_logical() {
+                    if left_type != Types::Bool || right_type != Types::Bool {
+                        return fail(span, operands("Bool"));
+                    }
+                } else if operator.is_ordering()
+                    && (left_type != Types::Number || right_type != Types::Number)
+                {
+                    return fail(span, operands("Num"));
+                } else if left_type != right_type {
+                    return fail(span, operands("the same type"));
+                } else if left_type.element().is_some()
+                    || matches!(left_type, Types::Struct(_) | Types::Enum(_))
+                {
+                    return fail(span, TypeError::Incomparable(left_type));
+                }
+                let expr = BoolExpr {
+                    left: Box::new(left),
+                    operator: operator.clone(),
+                    right: Box::new(right),
+                    span: span.clone(),
+                };
+                Ok((Bool::Expr(expr).to_value(), Types::Bool))
+            }
+        }
+    }
+
+    /// Checks the Vec literal `[a, b, c]`. The elements must all have the same type, which is
+    /// left as Nothing for `[]` until `value` knows which Vec is expected.
+    fn vec_literal(
+        &mut self,
+        elements: &[Expr],
+        span: &Span,
+    ) -> Result<(Value, Types), Diagnostic> {
+        let mut values = Vec::new();
+        let mut element = Types::Nothing;
+        for expr in elements {
+            let (value, found) = self.expression(expr)?;
+            if values.is_empty() {
+                if !matches!(found, Types::Number | Types::String | Types::Bool) {
+                    return fail(&expr.span, TypeError::VecElement(found));
+                }
+                element = found;
+            } else if found != element {
+                return fail(
+                    &expr.span,
+                    TypeError::Mismatch {
+                        expected: element,
+                        found,
+                    },
+                );
+            }
+            values.push(value);
+        }
+        let vectype = Types::Vec(Box::new(element.clone()));
+        let lit = VecLiteral {
+            elements: values,
+            element,
+            span: span.clone(),
+        };
+        Ok((Value::Vec(lit), vectype))
+    }
+
+    /// Checks `[index]` into a value of type `indexed`. A constant index is checked here, while
+    /// any other index is checked when the program runs.
+    fn subscript(&mut self, indexed: &Types, index: &Expr) -> Result<Number, Diagnostic> {
+        let span = &index.span;
+        let index = self.number(index)?;
+        if let Number::Lit(lit) = &index {
+            let outside = match indexed {
+                Types::Array(_, len) => lit.val < 0 || lit.val as usize >= *len,
+                _ => lit.val < 0,
+            };
+            if outside {
+                return reject(
+                    ErrorCode::Index,
+                    span,
+                    format!("Index {} is out of bounds for {indexed}", lit.val),
+                );
+            }
+        }
+        Ok(index)
+    }
+
+    /// Checks `Enum.Variant`, or `Enum.Variant { field: value, ... }` when `fields` are given.
+    fn variant(
+        &mut self,
+        enumeration: &EnumType,
+        variant: &String,
+        fields: Option<&Vec<FieldInit>>,
+        span: &Span,
+    ) -> Result<(Value, Types), Diagnostic> {
+        let name = &enumeration.name;
+        let Some(payload) = enumeration.variant(variant) else {
+            return fail(
+                span,
+                TypeError::NoVariant {
+                    owner: name.clone(),
+                    variant: variant.clone(),
+                },
+            );
+        };
+        let qualified = format!("{name}.{variant}");
+        let fields = match fields {
+            Some(fields) => self.literal_fields(payload, &qualified, fields, span)?,
+            None if !payload.fields.is_empty() => {
+                return reject(
+                    ErrorCode::NoMember,
+                    span,
+                    format!("{qualified} needs its fields, as {qualified} {{ ... }}"),
+                );
+            }
+            None => Vec::new(),
+        };
+        let lit = VariantLiteral {
+            variant: variant.clone(),
+            enumeration: enumeration.clone(),
+            fields,
+            span: span.clone(),
+        };
+        Ok((Value::Variant(lit), Types::Enum(enumeration.clone())))
+    }
+
+    /// Checks `{ field: value, ... }` for the struct or variant `structure`, called `name` in
+    /// errors. The values are returned in the order `structure` declares its fields.
+    fn literal_fields(
+        &mut self,
+        structure: &StructType,
+        name: &String,
+        fields: &[FieldInit],
+        span: &Span,
+    ) -> Result<Vec<Value>, Diagnostic> {
+        let mut given: Vec<(String, Value)> = Vec::new();
+        for field in fields {
+            let Some(field_type) = structure.field(&field.name).cloned() else {
+                return fail(
+                    &field.span,
+                    TypeError::NoField {
+                        owner: name.clone(),
+                        field: field.name.clone(),
+                    },
+                );
+            };
+            if given.iter().any(|(earlier, _)| *earlier == field.name) {
+                return reject(
+                    ErrorCode::Duplicate,
+                    &field.span,
+                    format!("Field {} is given more than once", field.name),
+                );
+            }
+            let value = self.value(&field_type, &field.value)?;
+            given.push((field.name.clone(), value));
+        }
+        let missing: Vec<&str> = structure
+            .fields
+            .iter()
+            .filter(|(field, _)| !given.iter().any(|(other, _)| other == field))
+            .map(|(field, _)| field.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return reject(
+                ErrorCode::NoMember,
+                span,
+                format!("Missing fields in {name} literal: {}", missing.join(", ")),
+            );
+        }
+        let mut values = Vec::new();
+        for (field, _) in &structure.fields {
+            let position = given
+                .iter()
+                .position(|(other, _)| other == field)
+                .ok_or_else(|| Diagnostic::internal(format!("Field {field} went missing")))?;
+            values.push(given.remove(position).1);
+        }
+        Ok(values)
+    }
+
+    /// Checks the lambda `(params) -> Type { ... }` used as a value. Its body sees the variables
+    /// of the enclosing functions, and gets its own copy of each one it uses.
+    fn lambda(&mut self, lambda: &FuncExpr, span: &Span) -> Result<(Value, Types), Diagnostic> {
+        self.open_lambdas.push(LambdaScope {
+            base: self.symbols.depth(),
+            captures: Vec::new(),
+        });
+        let func = self
+            .signature(lambda)
+            .and_then(|(params, ret)| self.function(&String::new(), params, ret, lambda));
+        let scope = self
+            .open_lambdas
+            .pop()
+            .ok_or_else(|| Diagnostic::internal("The scope of a lambda went missing"))?;
+        let mut func = func?;
+        for capture in &scope.captures {
+            let name = &capture.name;
+            if capture.mutable {
+                return reject(
+                    ErrorCode::Capture,
+                    span,
+                    format!("Lambdas capture by value, so they cannot use the mutable variable {name}. Copy it into an immutable variable first"),
+                );
+            }
+            if matches!(
+                capture.i_type,
+                Types::String | Types::Vec(_) | Types::Array(..)
+            ) {
+                return reject(
+                    ErrorCode::Unsupported,
+                    span,
+                    format!(
+                        "Capturing {name} of type {} in a lambda is coming soon",
+                        capture.i_type
+                    ),
+                );
+            }
+        }
+        // Lambdas inside this one end first, so counting the finished ones keeps names unique
+        func.name = format!("_lambda_{}", self.lambdas.len() + 1);
+        let signature = func.signature();
+        self.note_func_type(&signature);
+        let lambda = LambdaNode {
+            func,
+            captures: scope.captures,
+            span: span.clone(),
+        };
+        self.lambdas.push(lambda.clone());
+        Ok((Value::Lambda(lambda), Types::Function(signature)))
+    }
+
+    /// Checks a call, which may be to a builtin taking arguments of any type.
+    fn call(&mut self, call: &CallExpr) -> Result<CallNode, Diagnostic> {
+        let (name, span) = (&call.name, &call.span);
+        let ident = self.find(name, span)?;
+        let Some((func, indirect)) = Self::callable(&ident) else {
+            return fail(span, TypeError::NotAFunction(name.clone()));
+        };
+        match func.name.as_str() {
+            "showme" => return self.showme_call(func, call),
+            "rob_text_len" | "rob_vec_push" | "rob_vec_pop" => return self.vec_call(func, call),
+            "rob_vec_map" | "rob_vec_filter" => return self.callback_call(func, call),
+            _ => {}
+        }
+        Self::arity(call, func.params.len())?;
+        let mut params = Vec::new();
+        for (param, arg) in func.params.iter().zip(&call.args) {
+            let value = self.value(&param.i_type, arg)?;
+            params.push(argument(value)?);
+        }
+        Ok(CallNode {
+            func,
+            params,
+            indirect,
+            span: span.clone(),
+        })
+    }
+
+    fn arity(call: &CallExpr, expected: usize) -> Result<(), Diagnostic> {
+        if call.args.len() != expected {
+            return fail(
+                &call.span,
+                TypeError::Arity {
+                    name: call.name.clone(),
+                    expected,
+                    found: call.args.len(),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// The function a call through `ident` runs, and whether it is called indirectly. A variable
+    /// or parameter holding a function is called by its own name, with the signature of its
+    /// type.
+    fn callable(ident: &IdentifierNode) -> Option<(Function, bool)> {
+        if let Some(Value::Func(func)) = ident.value.as_deref() {
+            return Some((func.clone(), false));
+        }
+        let Types::Function(signature) = &ident.i_type else {
+            return None;
+        };
+        let params = signature
+            .params
+            .iter()
+            .map(|i_type| param("", i_type.clone()))
+            .collect();
+        let func = Function {
+            name: ident.name.clone(),
+            params,
+            ret: (*signature.ret).clone(),
+            body: BlockNode {
+                children: Vec::new(),
+                span: ident.declared.clone(),
+            },
+            span: ident.declared.clone(),
+        };
+        Some((func, true))
+    }
+
+    /// Checks a call to `length`, `push` or `pop`. These take a Vec of any element type, so the
+    /// signature of the call is built from the Vec that is passed. `length` also takes Text and
+    /// arrays.
+    fn vec_call(&mut self, func: Function, call: &CallExpr) -> Result<CallNode, Diagnostic> {
+        let name = match func.name.as_str() {
+            "rob_vec_push" => "push",
+            "rob_vec_pop" => "pop",
+            _ => "length",
+        };
+        Self::arity(call, if name == "push" { 2 } else { 1 })?;
+        let span = &call.args[0].span;
+        let (target, target_type) = self.expression(&call.args[0])?;
+        let mut params = vec![param("vec", target_type.clone())];
+        let mut args = vec![argument(target.clone())?];
+        let (cname, ret) = match (name, &target_type) {
+            ("length", Types::String) => ("rob_text_len", Types::Number),
+            ("length", Types::Vec(_)) => ("rob_vec_len", Types::Number),
+            ("length", Types::Array(_, len)) => {
+                // The length is part of the type, so it is handed over as it is
+                let len = Value::Lit(Literal::Num(NumLiteral {
+                    val: *len as i32,
+                    span: span.clone(),
+                }));
+                args.push(argument(len)?);
+                params.push(param("len", Types::Number));
+                ("rob_array_len", Types::Number)
+            }
+            (_, Types::Vec(element)) => {
+                // Both may move the elements, which needs a Vec that can be changed
+                let changeable = match &target {
+                    Value::Ident(node) => node.mutable,
+                    Value::Deref(node) => matches!(node.i_type, Types::MutPointer(_)),
+                    _ => false,
+                };
+                if !changeable {
+                    return reject(
+                        ErrorCode::Immutable,
+                        span,
+                        format!("Cannot {name} on a Vec that is not mutable. Declare it with 'let mut', or pass it as a *mut {target_type}"),
+                    );
+                }
+                let element = (**element).clone();
+                if name == "pop" {
+                    ("rob_vec_pop", element)
+                } else {
+                    args.push(argument(self.value(&element, &call.args[1])?)?);
+                    params.push(param("value", element.clone()));
+                    // A Vec of Text owns a copy of every element
+                    if element == Types::String {
+                        ("rob_vec_text_push", Types::Nothing)
+                    } else {
+                        ("rob_vec_push", Types::Nothing)
+                    }
+                }
+            }
+            _ => {
+                let expected = if name == "length" {
+                    "Text, a Vec or an array"
+                } else {
+                    "a Vec"
+                };
+                return fail(
+                    span,
+                    TypeError::Expects {
+                        name: String::from(name),
+                        expected,
+                        found: target_type,
+                    },
+                );
+            }
+        };
+        let func = Function {
+            name: String::from(cname),
+            params,
+            ret,
+            ..func
+        };
+        Ok(CallNode {
+            func,
+            params: args,
+            indirect: false,
+            span: call.span.clone(),
+        })
+    }
+
+    /// Checks a call to `map` or `filter`, which take a Vec of any element type and a function
+    /// to call on each element. The signature of the call is built from both.
+    fn callback_call(&mut self, func: Function, call: &CallExpr) -> Result<CallNode, Diagnostic> {
+        let name = if func.name == "rob_vec_map" {
+            "map"
+        } else {
+            "filter"
+        };
+        Self::arity(call, 2)?;
+        let (target, target_type) = self.expression(&call.args[0])?;
+        let Types::Vec(element) = &target_type else {
+            return fail(
+                &call.args[0].span,
+                TypeError::Expects {
+                    name: String::from(name),
+                    expected: "a Vec",
+                    found: target_type,
+                },
+            );
+        };
+        let funcspan = &call.args[1].span;
+        let (callback, callback_type) = self.expression(&call.args[1])?;
+        let Types::Function(signature) = &callback_type else {
+            return fail(
+                funcspan,
+                TypeError::Expects {
+                    name: String::from(name),
+                    expected: "a function",
+                    found: callback_type,
+                },
+            );
+        };
+        if signature.params != vec![(**element).clone()] {
+            return reject(
+                ErrorCode::Mismatch,
+                funcspan,
+                format!("{name} over a {target_type} expects a function taking one {element}, found {callback_type}"),
+            );
+        }
+        let ret = if name == "map" {
+            if !matches!(*signature.ret, Types::Number | Types::String | Types::Bool) {
+                return reject(
+                    ErrorCode::Mismatch,
+                    funcspan,
+                    format!("map expects a function returning Num, Text or Bool, the types a Vec can hold, found {callback_type}"),
+                );
+            }
+            Types::Vec(signature.ret.clone())
+        } else {
+            if *signature.ret != Types::Bool {
+                return reject(
+                    ErrorCode::Mismatch,
+                    funcspan,
+                    format!("filter expects a function returning Bool, found {callback_type}"),
+                );
+            }
+            target_type.clone()
+        };
+        self.note_func_type(signature);
+        let func = Function {
+            params: vec![
+                param("vec", target_type.clone()),
+                param("f", callback_type.clone()),
+            ],
+            ret,
+            ..func
+        };
+        let params = vec![argument(target)?, argument(callback)?];
+        Ok(CallNode {
+            func,
+            params,
+            indirect: false,
+            span: call.span.clone(),
+        })
+    }
+
+    /// Checks a call to `showme`, which takes any number of literals and variables.
+    fn showme_call(&mut self, func: Function, call: &CallExpr) -> Result<CallNode, Diagnostic> {
+        let mut params = Vec::new();
+        for arg in &call.args {
+            if !matches!(
+                arg.kind,
+                ExprKind::Num(_) | ExprKind::Bool(_) | ExprKind::Text(_) | ExprKind::Ident(_)
+            ) {
+                return reject(
+                    ErrorCode::NotAValue,
+                    &arg.span,
+                    "showme can only show literals and variables",
+                );
+            }
+            let (value, i_type) = self.expression(arg)?;
+            if let (Types::Function(_), ExprKind::Ident(name)) = (&i_type, &arg.kind) {
+                return reject(
+                    ErrorCode::NotAValue,
+                    &arg.span,
+                    format!("Cannot show the function {name}"),
+                );
+            }
+            params.push(argument(value)?);
+        }
+        Ok(CallNode {
+            func,
+            params,
+            indirect: false,
+            span: call.span.clone(),
+        })
+    }
+
+    fn conditional(&mut self, stmt: &IfStmt, ret: &Types) -> Result<ConditionalNode, Diagnostic> {
+        let condition = self.boolean(&stmt.condition)?;
+        let body = self.block(&stmt.body, Vec::new(), ret, &stmt.span)?;
+        let mut elifs = Vec::new();
+        for (condition, body) in &stmt.elifs {
+            let span = &condition.span;
+            let condition = self.boolean(condition)?;
+            let body = self.block(body, Vec::new(), ret, span)?;
+            elifs.push(ElseIfNode {
+                span: span.to(&body.span),
+                condition,
+                body,
+            });
+        }
+        let i_else = match &stmt.i_else {
+            Some(body) => Some(self.block(body, Vec::new(), ret, &stmt.span)?),
+            None => None,
+        };
+        Ok(ConditionalNode {
+            condition,
+            body,
+            elifs,
+            i_else,
+            span: stmt.span.clone(),
+        })
+    }
+
+    /// Checks that a return matches the return type `ret` of the enclosing function.
+    fn return_value(&mut self, stmt: &ReturnStmt, ret: &Types) -> Result<ReturnNode, Diagnostic> {
+        let span = &stmt.span;
+        let value = match &stmt.value {
+            None if *ret != Types::Nothing => {
+                return reject(
+                    ErrorCode::Return,
+                    span,
+                    format!("Expected a return value of type {ret}"),
+                );
+            }
+            None => Value::Nothing,
+            Some(_) if *ret == Types::Nothing => {
+                return reject(
+                    ErrorCode::Return,
+                    span,
+                    "Cannot return a value from a function returning Nothing",
+                );
+            }
+            Some(value) => self.value(ret, value)?,
+        };
+        Ok(ReturnNode {
+            value,
+            span: span.clone(),
+        })
+    }
+
+    /// The label of a loop labelled `name`, numbered to keep it unique in C.
+    fn loop_label(&mut self, name: &Option<String>) -> Option<LoopLabel> {
+        let name = name.as_ref()?;
+        self.label_count += 1;
+        Some(LoopLabel {
+            name: name.clone(),
+            id: self.label_count,
+        })
+    }
+
+    /// Checks the body of the loop at `span` with `label` pushed as the innermost enclosing loop.
+    /// A loop without a label is given one if the body breaks out of it from inside a `match`.
+    fn loop_body(
+        &mut self,
+        body: &[Stmt],
+        params: Vec<IdentifierNode>,
+        ret: &Types,
+        span: &Span,
+        label: &mut Option<LoopLabel>,
+    ) -> Result<BlockNode, Diagnostic> {
+        self.loops.push(label.clone());
+        let outermatches = std::mem::take(&mut self.match_depth);
+        let body = self.block(body, params, ret, span);
+        self.match_depth = outermatches;
+        *label = self.loops.pop().flatten();
+        body
+    }
+
+    fn while_loop(&mut self, stmt: &WhileStmt, ret: &Types) -> Result<WhileNode, Diagnostic> {
+        let mut label = self.loop_label(&stmt.label);
+        let condition = self.boolean(&stmt.condition)?;
+        let body = self.loop_body(&stmt.body, Vec::new(), ret, &stmt.span, &mut label)?;
+        Ok(WhileNode {
+            condition,
+            body,
+            label,
+            span: stmt.span.clone(),
+        })
+    }
+
+    /// Checks `for x in start..end { ... }` or `for x in vec { ... }`.
+    fn for_loop(&mut self, stmt: &ForStmt, ret: &Types) -> Result<ForNode, Diagnostic> {
+        let mut label = self.loop_label(&stmt.label);
+        let mut ident = IdentifierNode {
+            name: stmt.name.clone(),
+            i_type: Types::Number,
+            value: None,
+            mutable: false,
+            declared: stmt.span.clone(),
+            span: stmt.span.clone(),
+        };
+        let iter = match &stmt.iter {
+            ForIter::Range(start, end) => {
+                let start = self.number(start)?;
+                let end = self.number(end)?;
+                let span = start.span().to(end.span());
+                Iterable::Range(RangeNode { start, end, span })
+            }
+            ForIter::Elements(elements) => {
+                let span = &elements.span;
+                let (value, found) = self.expression(elements)?;
+                match found.element() {
+                    Some(Types::Nothing) => {
+                        return reject(
+                            ErrorCode::Element,
+                            span,
+                            "Cannot loop over an empty Vec literal",
+                        );
+                    }
+                    Some(element) => {
+                        ident.i_type = element.clone();
+                        Iterable::Elements(value)
+                    }
+                    None => {
+                        return reject(
+                            ErrorCode::Element,
+                            span,
+                            format!("Can only loop over a range, a Vec or an array, found {found}"),
+                        );
+                    }
+                }
+            }
+        };
+        let params = vec![ident.clone()];
+        let body = self.loop_body(&stmt.body, params, ret, &stmt.span, &mut label)?;
+        Ok(ForNode {
+            dec: ident,
+            iter,
+            body,
+            label,
+            span: stmt.span.clone(),
+        })
+    }
+
+    /// Checks a `break` or `continue`, which must be inside a loop of the current function and
+    /// may name the loop it applies to.
+    fn loop_control(&mut self, keyword: &str, stmt: &JumpStmt) -> Result<LoopControl, Diagnostic> {
+        let span = &stmt.span;
+        if self.loops.is_empty() {
+            return reject(
+                ErrorCode::Loop,
+                span,
+                format!("Cannot {keyword} outside of a loop"),
+            );
+        }
+        let mut label = None;
+        if let Some(name) = &stmt.label {
+            let found = self
+                .loops
+                .iter()
+                .rev()
+                .flatten()
+                .find(|somelabel| somelabel.name == *name);
+            let Some(found) = found else {
+                return reject(
+                    ErrorCode::Loop,
+                    span,
+                    format!("No enclosing loop is labelled '{name}"),
+                );
+            };
+            label = Some(found.clone());
+        } else if keyword == "break" && self.match_depth > 0 {
+            // A plain break in C would only leave the switch of the match, so the loop gets a
+            // label to jump to
+            self.label_count += 1;
+            let id = self.label_count;
+            let innermost = self
+                .loops
+                .last_mut()
+                .ok_or_else(|| Diagnostic::internal("A break has no loop to apply to"))?
+                .get_or_insert(LoopLabel {
+                    name: String::from("loop"),
+                    id,
+                });
+            label = Some(innermost.clone());
+        }
+        Ok(LoopControl {
+            label,
+            span: span.clone(),
+        })
+    }
+
+    /// Checks `match value { ... }`. Every variant of the enum needs an arm, unless the last arm
+    /// is `_`.
+    fn match_stmt(&mut self, stmt: &MatchStmt, ret: &Types) -> Result<MatchNode, Diagnostic> {
+        let span = &stmt.span;
+        let (value, found) = self.expression(&stmt.value)?;
+        let Types::Enum(enumeration) = found else {
+            return fail(
+                span,
+                TypeError::Expects {
+                    name: String::from("match"),
+                    expected: "an enum",
+                    found,
+                },
+            );
+        };
+        let name = &enumeration.name;
+        let mut arms: Vec<MatchArm> = Vec::new();
+        for arm in &stmt.arms {
+            if arms.last().is_some_and(|arm| arm.variant.is_none()) {
+                return reject(
+                    ErrorCode::Match,
+                    &arm.span,
+                    "The '_' arm must be the last arm of a match",
+                );
+            }
+            let mut bindings = Vec::new();
+            if let Some(variant) = &arm.variant {
+                let Some(payload) = enumeration.variant(variant) else {
+                    return fail(
+                        &arm.span,
+                        TypeError::NoVariant {
+                            owner: name.clone(),
+                            variant: variant.clone(),
+                        },
+                    );
+                };
+                if arms.iter().any(|arm| arm.variant.as_ref() == Some(variant)) {
+                    return reject(
+                        ErrorCode::Match,
+                        &arm.span,
+                        format!("Variant {variant} is matched more than once"),
+                    );
+                }
+                bindings = Self::bindings(payload, &format!("{name}.{variant}"), &arm.bindings)?;
+            }
+            let params = bindings.iter().map(|(_, ident)| ident.clone()).collect();
+            self.match_depth += 1;
+            let body = self.block(&arm.body, params, ret, &arm.span);
+            self.match_depth -= 1;
+            arms.push(MatchArm {
+                variant: arm.variant.clone(),
+                bindings,
+                body: body?,
+                span: arm.span.clone(),
+            });
+        }
+        if !arms.last().is_some_and(|arm| arm.variant.is_none()) {
+            let missing: Vec<&str> = enumeration
+                .variants
+                .iter()
+                .filter(|variant| {
+                    !arms
+                        .iter()
+                        .any(|arm| arm.variant.as_ref() == Some(&variant.name))
+                })
+                .map(|variant| variant.name.as_str())
+                .collect();
+            if !missing.is_empty() {
+                return reject(
+                    ErrorCode::Match,
+                    span,
+                    format!(
+                        "Match on {name} is missing variants: {}",
+                        missing.join(", ")
+                    ),
+                );
+            }
+        }
+        self.match_count += 1;
+        Ok(MatchNode {
+            value,
+            enumeration: enumeration.clone(),
+            arms,
+            id: self.match_count,
+            span: span.clone(),
+        })
+    }
+
+    /// The fields of the variant `payload`, called `name` in errors, bound by a match arm.
+    fn bindings(
+        payload: &StructType,
+        name: &String,
+        bindings: &[Binding],
+    ) -> Result<Vec<(String, IdentifierNode)>, Diagnostic> {
+        let mut bound: Vec<(String, IdentifierNode)> = Vec::new();
+        for binding in bindings {
+            let field = &binding.field;
+            let Some(i_type) = payload.field(field).cloned() else {
+                return fail(
+                    &binding.span,
+                    TypeError::NoField {
+                        owner: name.clone(),
+                        field: field.clone(),
+                    },
+                );
+            };
+            if bound.iter().any(|(earlier, _)| earlier == field) {
+                return reject(
+                    ErrorCode::Duplicate,
+                    &binding.span,
+                    format!("Field {field} is bound more than once"),
+                );
+            }
+            if bound
+                .iter()
+                .any(|(_, earlier)| earlier.name == binding.name)
+            {
+                return reject(
+                    ErrorCode::Duplicate,
+                    &binding.span,
+                    format!("{} is bound more than once", binding.name),
+                );
+            }
+            let ident = IdentifierNode {
+                name: binding.name.clone(),
+                i_type,
+                value: None,
+                mutable: false,
+                declared: binding.span.clone(),
+                span: binding.span.clone(),
+            };
+            bound.push((field.clone(), ident));
+        }
+        Ok(bound)
+    }
+}
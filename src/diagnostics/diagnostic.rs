@@ -0,0 +1,255 @@
+use std::fmt;
+use std::rc::Rc;
+
+/// Something wrong with the program being compiled, found by any stage of the compiler. Stages
+/// hand diagnostics back up instead of printing them, and `main` reports them.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: ErrorCode,
+    pub message: String,
+    /// Where the problem is. None for problems with the program as a whole, such as a missing
+    /// main function.
+    pub span: Option<Span>,
+    /// Other places involved, such as the declaration of a variable that is misused.
+    pub labels: Vec<Label>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// Stops the build.
+    Error,
+    /// Reported, but the build goes on.
+    Warning,
+}
+
+/// A place in the source, from the first byte of a token or node up to the byte after its
+/// end. Builtins have no place in the source and get the default span, on line 0.
+#[derive(Clone, PartialEq, Default)]
+pub struct Span {
+    /// The file as it was given to the compiler.
+    pub file: Rc<str>,
+    /// Line of the first byte, counting from 1.
+    pub line: usize,
+    /// Column of the first byte in characters, counting from 1.
+    pub column: usize,
+    /// Byte offset of the first byte.
+    pub start: usize,
+    /// Byte offset just past the last byte.
+    pub end: usize,
+}
+
+impl Span {
+    /// From the start of this span to the end of `last`, which comes after it.
+    pub fn to(&self, last: &Span) -> Span {
+        Span {
+            end: self.end.max(last.end),
+            ..self.clone()
+        }
+    }
+
+    /// Whether this is the span of a builtin, which has no place in the source.
+    pub fn is_builtin(&self) -> bool {
+        self.line == 0
+    }
+}
+
+/// A secondary place a diagnostic points at, with what it has to do with the problem.
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Stable code for each kind of diagnostic, so tools can tell them apart without reading the
+/// message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCode {
+    /// A file could not be read or written.
+    Io,
+    /// A Text literal without its closing quote.
+    UnterminatedText,
+    /// A number literal outside the range of a Num.
+    NumberRange,
+    /// The tokens do not make up a valid program.
+    Syntax,
+    /// Something the language will have, but does not have yet.
+    Unsupported,
+    /// A statement or declaration in a place it cannot be.
+    Placement,
+    /// A name that is not declared where it is used.
+    UnknownName,
+    /// A type that is not declared, or a name used as a type that is not one.
+    UnknownType,
+    /// The same name declared twice, or the same field or variant given twice.
+    Duplicate,
+    /// A value of the wrong type.
+    Mismatch,
+    /// Two values that cannot be compared.
+    Incomparable,
+    /// Something used as a value that cannot be one, such as a type.
+    NotAValue,
+    /// A call to something that is not a function.
+    NotAFunction,
+    /// A call with the wrong number of arguments.
+    Arity,
+    /// A field or variant that does not exist, or fields that are missing.
+    NoMember,
+    /// Indexing something that cannot be indexed, or a constant index out of bounds.
+    Index,
+    /// Dereferencing something that is not a pointer.
+    Deref,
+    /// A type that cannot be the element of a Vec or array.
+    Element,
+    /// A return that does not fit the function it is in.
+    Return,
+    /// Changing something that is not mutable, or marking something mut that cannot be.
+    Immutable,
+    /// Reading a variable before it is definitely assigned.
+    Unassigned,
+    /// A break or continue without a loop to apply to.
+    Loop,
+    /// A match that is missing variants or has arms out of place.
+    Match,
+    /// A lambda using a variable it cannot capture.
+    Capture,
+    /// A constant that cannot be worked out at compile time, or whose arithmetic fails.
+    Constant,
+    /// A pointer that outlives what it points to.
+    Escape,
+    /// A program without a main function.
+    NoMain,
+    /// A bug in the compiler.
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::Io => "E0001",
+            ErrorCode::UnterminatedText => "E0101",
+            ErrorCode::NumberRange => "E0102",
+            ErrorCode::Syntax => "E0201",
+            ErrorCode::Unsupported => "E0202",
+            ErrorCode::Placement => "E0203",
+            ErrorCode::UnknownName => "E0301",
+            ErrorCode::UnknownType => "E0302",
+            ErrorCode::Duplicate => "E0303",
+            ErrorCode::Mismatch => "E0304",
+            ErrorCode::Incomparable => "E0305",
+            ErrorCode::NotAValue => "E0306",
+            ErrorCode::NotAFunction => "E0307",
+            ErrorCode::Arity => "E0308",
+            ErrorCode::NoMember => "E0309",
+            ErrorCode::Index => "E0310",
+            ErrorCode::Deref => "E0311",
+            ErrorCode::Element => "E0312",
+            ErrorCode::Return => "E0313",
+            ErrorCode::Immutable => "E0401",
+            ErrorCode::Unassigned => "E0402",
+            ErrorCode::Loop => "E0501",
+            ErrorCode::Match => "E0502",
+            ErrorCode::Capture => "E0503",
+            ErrorCode::Constant => "E0601",
+            ErrorCode::Escape => "E0701",
+            ErrorCode::NoMain => "E0801",
+            ErrorCode::Internal => "E9001",
+        }
+    }
+}
+
+impl Diagnostic {
+    /// An error at `span`.
+    pub fn error(code: ErrorCode, span: &Span, message: impl fmt::Display) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message: message.to_string(),
+            span: Some(span.clone()),
+            labels: Vec::new(),
+        }
+    }
+
+    /// An error that is not about any one place in the source.
+    pub fn global(code: ErrorCode, message: impl fmt::Display) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message: message.to_string(),
+            span: None,
+            labels: Vec::new(),
+        }
+    }
+
+    /// A state an earlier stage should have ruled out.
+    pub fn internal(message: impl fmt::Display) -> Self {
+        Self::global(
+            ErrorCode::Internal,
+            format!("Oops! {message}. Please submit an issue on github."),
+        )
+    }
+
+    /// Adds `message` about `span`. Builtins have no place in the source to point at, so a
+    /// label on one is left out.
+    pub fn with_label(mut self, span: &Span, message: impl fmt::Display) -> Self {
+        if !span.is_builtin() {
+            self.labels.push(Label {
+                span: span.clone(),
+                message: message.to_string(),
+            });
+        }
+        self
+    }
+}
+
+/// Lets a stage that reports every problem it finds share `?` with the stages that stop at the
+/// first one.
+impl From<Diagnostic> for Vec<Diagnostic> {
+    fn from(diagnostic: Diagnostic) -> Self {
+        vec![diagnostic]
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    /// `file.rob:3:5`, the way compilers and editors point at a place in a file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+impl fmt::Debug for Span {
+    /// Kept short, as every token and node holds a span.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}@{}..{}", self.start, self.end)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// `error[E0304]: file.rob:3:5: message`, followed by a line for every label.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: ", self.severity, self.code)?;
+        if let Some(span) = &self.span {
+            write!(f, "{span}: ")?;
+        }
+        write!(f, "{}", self.message)?;
+        for label in &self.labels {
+            write!(f, "\n{}: {}", label.span, label.message)?;
+        }
+        Ok(())
+    }
+}
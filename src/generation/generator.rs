@@ -1,163 +1,550 @@
+use crate::diagnostics::diagnostic::{Diagnostic, ErrorCode};
 use crate::parsing::nodes::*;
 use std::fs;
-use std::io::prelude::*;
 
 pub struct Generator {
     root: Program,
+    /// The .rob file being compiled, named when an index is out of bounds at runtime.
+    file: String,
+    bounds_checks: bool,
 }
 
 impl Generator {
-    pub fn new(prog: Program) -> Self {
-        Generator { root: prog }
+    pub fn new(prog: Program, file: &String, bounds_checks: bool) -> Self {
+        Generator {
+            root: prog,
+            file: file.clone(),
+            bounds_checks,
+        }
     }
 
-    fn c_out(&self) -> Option<String> {
-        let Some(res) = self.root.c_out() else {
-            eprintln!("Generator c_out on call to program c_out");
-            return None;
-        };
-        Some(res)
+    fn c_out(&self) -> Result<String, Diagnostic> {
+        self.root.c_out()
     }
 
-    fn baseimports() -> String {
-        String::from("#include \"robIO.h\"\n")
+    fn baseimports(&self) -> String {
+        let mut res = format!("#define ROB_FILE {:?}\n", self.file);
+        if !self.bounds_checks {
+            res.push_str("#define ROB_NO_BOUNDS_CHECKS\n");
+        }
+        res.push_str("#include \"robIO.h\"\n");
+        res
     }
 
-    pub fn write(&self) -> std::io::Result<Option<()>> {
-        let Some(writematerial) = self.c_out() else {
-            return Ok(None);
-        };
-        let mut newfile = fs::File::create("out.c")?;
-        newfile.write_all(Self::baseimports().as_bytes());
-        newfile.write_all(writematerial.as_bytes())?;
-        Ok(Some(()))
+    /// Definitions of every struct and enum, which come first as any function may use them.
+    fn structs(&self) -> Result<String, Diagnostic> {
+        let mut res = String::new();
+        let mut defined = Vec::new();
+        for child in &self.root.children {
+            let StatementNode::DeclareAssign(node) = child else {
+                continue;
+            };
+            if let Some(Value::Type(i_type)) = node.ident.value.as_deref() {
+                define_c_out(i_type, &mut defined, &mut res)?;
+            }
+        }
+        Ok(res)
+    }
+
+    /// Typedefs for every function type, after the structs and enums their signatures may use.
+    fn typedefs(&self) -> Result<String, Diagnostic> {
+        let mut res = String::new();
+        for signature in &self.root.func_types {
+            res.push_str(&signature.typedef_c_out()?);
+        }
+        Ok(res)
+    }
+
+    /// Every top level constant, which functions defined before it may use as well.
+    fn constants(&self) -> Result<String, Diagnostic> {
+        let mut res = String::new();
+        for child in &self.root.children {
+            let StatementNode::DeclareAssign(node) = child else {
+                continue;
+            };
+            if let Some(Value::Lit(lit)) = node.ident.value.as_deref() {
+                // Only the pointer to constant Text is const, as it is passed around as char*
+                let decl = match node.i_type {
+                    Types::String => format!("char* const {}", node.ident.name),
+                    _ => format!("const {} {}", node.i_type.c_out()?, node.ident.name),
+                };
+                res.push_str(&format!("static {decl} = {};\n", lit.c_out()?));
+            }
+            // Constant arrays are written out element by element, as C cannot fill them
+            if let Some(Value::Array(lit)) = node.ident.value.as_deref() {
+                let element = lit.value.c_out()?;
+                res.push_str(&format!(
+                    "static const {} {}[{}] = {{{}}};\n",
+                    lit.element.c_out()?,
+                    node.ident.name,
+                    lit.len,
+                    vec![element; lit.len].join(", ")
+                ));
+            }
+        }
+        Ok(res)
+    }
+
+    /// Environments of the lambdas, which may hold function values and so come after the
+    /// typedefs.
+    fn environments(&self) -> Result<String, Diagnostic> {
+        let mut res = String::new();
+        for lambda in &self.root.lambdas {
+            if !lambda.captures.is_empty() {
+                res.push_str(&lambda.env_c_out()?);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Prototypes for every top level function and lifted lambda, so definitions can come in
+    /// any order.
+    fn prototypes(&self) -> Result<String, Diagnostic> {
+        let mut res = String::new();
+        for child in &self.root.children {
+            let StatementNode::DeclareAssign(node) = child else {
+                continue;
+            };
+            if let Some(Value::Func(func)) = node.ident.value.as_deref() {
+                res.push_str(&format!("{};\n", func.signature_c_out()?));
+            }
+        }
+        for lambda in &self.root.lambdas {
+            res.push_str(&format!("{};\n", lambda.signature_c_out()?));
+        }
+        Ok(res)
+    }
+
+    pub fn write(&self) -> Result<(), Diagnostic> {
+        let structs = self.structs()?;
+        let typedefs = self.typedefs()?;
+        let environments = self.environments()?;
+        let constants = self.constants()?;
+        let prototypes = self.prototypes()?;
+        let writematerial = self.c_out()?;
+        let material = [
+            self.baseimports(),
+            structs,
+            typedefs,
+            environments,
+            constants,
+            prototypes,
+            writematerial,
+        ]
+        .concat();
+        fs::write("out.c", material).map_err(|error| {
+            Diagnostic::global(ErrorCode::Io, format!("Could not write out.c: {error}"))
+        })
     }
 }
 
 impl Program {
-    pub fn c_out(&self) -> Option<String> {
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
         let mut res = String::from("");
         for child in &self.children {
             match child {
+                // Constants are written ahead of the prototypes
+                StatementNode::DeclareAssign(node)
+                    if matches!(
+                        node.ident.value.as_deref(),
+                        Some(Value::Lit(_) | Value::Array(_))
+                    ) => {}
                 StatementNode::DeclareAssign(node) => {
-                    let Some(nodeout) = node.c_out() else {
-                        eprintln!("Program c_out on call to declare assign c_out");
-                        return None;
-                    };
+                    let nodeout = node.c_out()?;
                     res.push_str(&nodeout);
                 }
                 _ => {}
             }
         }
-        Some(res)
+        for lambda in &self.lambdas {
+            res.push_str(&lambda.c_out()?);
+        }
+        Ok(res)
     }
 }
 
 impl ReturnNode {
-    pub fn c_out(&self) -> Option<String> {
-        let mut res = String::from("");
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
         match &self.value {
-            Value::Ident(node) => {
-                res = format!("return {};\n", node.name.clone());
+            Value::Nothing => Ok(String::from("return;\n")),
+            // The caller owns returned Text, as everything in this function's scopes is released
+            value if value.get_type() == Some(Types::String) => {
+                Ok(format!("return rob_text_clone({});\n", value.c_out()?))
             }
-            _ => {}
+            value if matches!(value.get_type(), Some(Types::Vec(_))) => {
+                let Some(Types::Vec(element)) = value.get_type() else {
+                    return Err(Diagnostic::internal("A Vec changed type"));
+                };
+                Ok(format!(
+                    "return {}_copy({});\n",
+                    element.vec_helpers(),
+                    value.c_out()?
+                ))
+            }
+            value => Ok(format!("return {};\n", value.c_out()?)),
         }
-        Some(res)
     }
 }
 
 impl BlockNode {
-    pub fn c_out(&self) -> Option<String> {
-        let mut res = String::from("");
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        // Owns the temporary Text created by the statements of this block
+        let mut res = String::from("ROB_SCOPE;\n");
         for stmt in &self.children {
             match stmt {
                 StatementNode::DeclareAssign(node) => {
-                    let Some(outstr) = node.c_out() else {
-                        eprintln!("Generator blocknode c_out for DecAssignNode failed");
-                        return None;
-                    };
+                    let outstr = node.c_out()?;
                     res.push_str(&outstr);
                 }
+                StatementNode::Declare(node) => {
+                    let nodeout = node.c_out()?;
+                    res.push_str(&nodeout);
+                }
+                StatementNode::Assign(node) => {
+                    let nodeout = node.c_out()?;
+                    res.push_str(&nodeout);
+                }
                 StatementNode::Return(node) => {
-                    let Some(nodeout) = node.c_out() else {
-                        eprintln!("Generation failed in program c_out upon return node c_out call");
-                        return None;
-                    };
+                    let nodeout = node.c_out()?;
                     res.push_str(&nodeout);
                 }
                 StatementNode::Conditional(node) => {
-                    let Some(nodeout) = node.c_out() else {
-                        eprintln!("Generation failed in blocknode c_out for CondNode");
-                        return None;
-                    };
+                    let nodeout = node.c_out()?;
                     res.push_str(&nodeout);
                 }
                 StatementNode::Call(somecall) => {
-                    let Some(mut callstr) = somecall.c_out() else {
-                        eprintln!("Declare assign c_out upon call to call c_out");
-                        return None;
-                    };
+                    let mut callstr = somecall.c_out()?;
                     let fstr = format!("{callstr};",);
                     res.push_str(&fstr);
                 }
                 StatementNode::Inline(someinline) => {
-                    let InlineC(inlc) = someinline;
-                    res.push_str(inlc);
+                    res.push_str(&someinline.code);
                 }
                 StatementNode::ForLoop(somefor) => {
-                    let Some(forstr) = somefor.c_out() else {
-                        eprintln!("Generation failed upon for loop c_out call");
-                        return None;
-                    };
+                    let forstr = somefor.c_out()?;
                     res.push_str(&forstr);
                 }
+                StatementNode::WhileLoop(somewhile) => {
+                    let whilestr = somewhile.c_out()?;
+                    res.push_str(&whilestr);
+                }
+                StatementNode::Break(node) => res.push_str(&node.c_out("break")),
+                StatementNode::Continue(node) => res.push_str(&node.c_out("continue")),
+                StatementNode::Match(node) => {
+                    let matchstr = node.c_out()?;
+                    res.push_str(&matchstr);
+                }
                 _ => {}
             }
         }
         res.push('\n');
-        Some(res)
+        Ok(res)
     }
 }
 
 impl ForNode {
-    pub fn c_out(&self) -> Option<String> {
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
         let bod = self.body.c_out()?;
-        let start = match &self.range.start {
-            Number::Lit(somelit) => format!("{}", somelit.val),
-            Number::Ident(someident) => someident.c_out()?,
-            Number::Exp(someexp) => (*someexp).c_out()?,
-            Number::Call(somecall) => somecall.c_out()?,
-        };
-
-        let end = match &self.range.end {
-            Number::Lit(somelit) => format!("{}", somelit.val),
-            Number::Ident(someident) => someident.c_out()?,
-            Number::Exp(someexp) => (*someexp).c_out()?,
-            Number::Call(somecall) => somecall.c_out()?,
+        let range = match &self.iter {
+            Iterable::Range(range) => range,
+            Iterable::Elements(vec) => return self.elements_c_out(vec, bod),
         };
+        let start = range.start.c_out()?;
+        let end = range.end.c_out()?;
 
         let outstr = format!(
-            "for (int {} = {}; {} < {}; {}++) {{\n{}}}",
-            self.dec.name, start, self.dec.name, end, self.dec.name, bod
+            "for (int {} = {}; {} < {}; {}++) {{\n{{\n{}}}\n{}}}\n{}",
+            self.dec.name,
+            start,
+            self.dec.name,
+            end,
+            self.dec.name,
+            bod,
+            continue_target(&self.label),
+            break_target(&self.label)
         );
-        Some(outstr)
+        Ok(outstr)
+    }
+}
+
+impl ForNode {
+    /// Loops over the indices of the Vec or array. A Vec held by a variable is read again on
+    /// every iteration, as the body may grow it, while any other Vec is created once up front.
+    fn elements_c_out(&self, vec: &Value, bod: String) -> Result<String, Diagnostic> {
+        let name = &self.dec.name;
+        let (setup, vecstr) = match vec {
+            Value::Ident(_) | Value::Deref(_) => (String::new(), vec.c_out()?),
+            _ => (
+                format!(
+                    "{} _{name}_vec = {};\n",
+                    type_of(vec)?.c_out()?,
+                    vec.c_out()?
+                ),
+                format!("_{name}_vec"),
+            ),
+        };
+        let len = match type_of(vec)? {
+            Types::Array(_, len) => len.to_string(),
+            _ => format!("rob_vec_len({vecstr})"),
+        };
+        Ok(format!(
+            "{{\n{setup}for (int _{name}_index = 0; _{name}_index < {len}; _{name}_index++) {{\n{} {name} = {vecstr}[_{name}_index];\n{{\n{bod}}}\n{}}}\n}}\n{}",
+            self.dec.i_type.c_out()?,
+            continue_target(&self.label),
+            break_target(&self.label)
+        ))
+    }
+}
+
+impl WhileNode {
+    /// The condition is checked in a block of its own, so the temporaries it creates are freed
+    /// on every iteration rather than when the enclosing block ends.
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        let cond = self.condition.c_out()?;
+        let bod = self.body.c_out()?;
+        Ok(format!(
+            "while (1) {{\n{{\nROB_SCOPE;\nif (!({cond})) break;\n}}\n{{\n{bod}}}\n{}}}\n{}",
+            continue_target(&self.label),
+            break_target(&self.label)
+        ))
+    }
+}
+
+/// Adds the definition of the struct or enum `i_type` to `res`, after the definitions of the
+/// structs and enums its fields hold, as C needs those complete first. `defined` holds the names
+/// of those added so far.
+fn define_c_out(
+    i_type: &Types,
+    defined: &mut Vec<String>,
+    res: &mut String,
+) -> Result<(), Diagnostic> {
+    let (name, fields): (&String, Vec<&Types>) = match i_type {
+        Types::Struct(structure) => (
+            &structure.name,
+            structure.fields.iter().map(|(_, i_type)| i_type).collect(),
+        ),
+        Types::Enum(enumeration) => (
+            &enumeration.name,
+            enumeration
+                .variants
+                .iter()
+                .flat_map(|variant| variant.fields.iter().map(|(_, i_type)| i_type))
+                .collect(),
+        ),
+        _ => return Ok(()),
+    };
+    if defined.contains(name) {
+        return Ok(());
+    }
+    defined.push(name.clone());
+    for field in fields {
+        define_c_out(field, defined, res)?;
+    }
+    match i_type {
+        Types::Struct(structure) => res.push_str(&structure.c_out()?),
+        Types::Enum(enumeration) => res.push_str(&enumeration.c_out()?),
+        _ => {}
+    }
+    Ok(())
+}
+
+impl LoopLabel {
+    fn c_name(&self, kind: &str) -> String {
+        format!("{}_{}_{kind}", self.name, self.id)
+    }
+}
+
+/// Labelled loops end with a label that `continue 'label` jumps to. It sits outside the block of
+/// the body, so the jump never enters the scope of a variable declared after it.
+fn continue_target(label: &Option<LoopLabel>) -> String {
+    match label {
+        Some(label) => format!("{}:;\n", label.c_name("continue")),
+        None => String::new(),
+    }
+}
+
+/// Labelled loops are followed by a label that `break 'label` jumps to.
+fn break_target(label: &Option<LoopLabel>) -> String {
+    match label {
+        Some(label) => format!("{}:;\n", label.c_name("break")),
+        None => String::new(),
+    }
+}
+
+/// The value assigned to `ident`, which typeck gives every identifier it generates code for.
+fn value_of(ident: &IdentifierNode) -> Result<&Value, Diagnostic> {
+    ident
+        .value
+        .as_deref()
+        .ok_or_else(|| Diagnostic::internal(format!("{} has no value", ident.name)))
+}
+
+fn type_of(value: &Value) -> Result<Types, Diagnostic> {
+    value
+        .get_type()
+        .ok_or_else(|| Diagnostic::internal("A value has no type"))
+}
+
+impl LoopControl {
+    pub fn c_out(&self, kind: &str) -> String {
+        match &self.label {
+            Some(label) => format!("goto {};\n", label.c_name(kind)),
+            None => format!("{kind};\n"),
+        }
+    }
+}
+
+impl DeclareNode {
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        let prefix = match &self.i_type {
+            Types::Number => "int",
+            Types::Bool => "bool",
+            Types::String => {
+                return Ok(format!("ROB_OWNED char* {} = NULL;\n", self.ident.name));
+            }
+            Types::Pointer(_)
+            | Types::MutPointer(_)
+            | Types::Struct(_)
+            | Types::Enum(_)
+            | Types::Function(_) => {
+                return Ok(format!("{} {};\n", self.i_type.c_out()?, self.ident.name));
+            }
+            Types::Vec(element) => {
+                return Ok(format!(
+                    "{} {} {} = NULL;\n",
+                    element.vec_owner(),
+                    self.i_type.c_out()?,
+                    self.ident.name
+                ));
+            }
+            Types::Array(element, len) => {
+                return Ok(format!(
+                    "{} {}[{len}];\n",
+                    element.c_out()?,
+                    self.ident.name
+                ));
+            }
+            _ => {
+                return Err(Diagnostic::internal(format!(
+                    "A {} was declared without a value",
+                    self.i_type
+                )));
+            }
+        };
+        Ok(format!("{prefix} {};\n", self.ident.name))
+    }
+}
+
+impl AssignNode {
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        let value = value_of(&self.ident)?;
+        let text = value.get_type() == Some(Types::String);
+        let value = value.c_out()?;
+        if let Some(index) = &self.index {
+            let element = index_c_out(&self.ident, index, self.span.line)?;
+            if text {
+                return Ok(format!("rob_text_set(&{element}, {value});\n"));
+            }
+            return Ok(format!("{element} = {value};\n"));
+        }
+        if !self.fields.is_empty() {
+            let field = field_c_out(&self.ident, &self.fields)?;
+            if let Some(Types::Array(element, len)) = value_of(&self.ident)?.get_type() {
+                return array_store_c_out(&field, &element, len, value_of(&self.ident)?);
+            }
+            return Ok(format!("{field} = {value};\n"));
+        }
+        let target = match self.ident.i_type.pointee() {
+            Some(pointee) if self.deref => pointee,
+            _ => &self.ident.i_type,
+        };
+        match target {
+            Types::Vec(element) => {
+                // The variable keeps a copy, releasing the Vec it held before
+                let slot = if self.deref {
+                    self.ident.name.clone()
+                } else {
+                    format!("&{}", self.ident.name)
+                };
+                return Ok(format!("{}_set({slot}, {value});\n", element.vec_helpers()));
+            }
+            // A pointer to an array already points to its first element
+            Types::Array(element, len) => {
+                let value = value_of(&self.ident)?;
+                return array_store_c_out(&self.ident.name, element, *len, value);
+            }
+            _ => {}
+        }
+        match (self.deref, text) {
+            (true, true) => return Ok(format!("rob_text_set({}, {});\n", self.ident.name, value)),
+            (false, true) => {
+                return Ok(format!("rob_text_set(&{}, {});\n", self.ident.name, value))
+            }
+            (true, false) => return Ok(format!("*{} = {};\n", self.ident.name, value)),
+            (false, false) => {}
+        }
+        Ok(format!("{} = {};\n", self.ident.name, value))
     }
 }
 
 impl DecAssignNode {
-    fn c_out(&self) -> Option<String> {
+    fn c_out(&self) -> Result<String, Diagnostic> {
+        if self.i_type == Types::Type {
+            // Struct and enum definitions are written ahead of the prototypes
+            return Ok(String::new());
+        }
+        if self.i_type == Types::String {
+            // Every Text variable owns a copy, released at the end of its scope
+            let value = value_of(&self.ident)?.c_out()?;
+            return Ok(format!(
+                "ROB_OWNED char* {} = rob_text_clone({});\n",
+                self.ident.name, value
+            ));
+        }
+        // A function value, as opposed to the definition of a function
+        let holds_function = matches!(self.i_type, Types::Function(_))
+            && !matches!(self.ident.value.as_deref(), Some(Value::Func(_)));
+        if self.i_type.pointee().is_some()
+            || matches!(self.i_type, Types::Struct(_) | Types::Enum(_))
+            || holds_function
+        {
+            let value = value_of(&self.ident)?.c_out()?;
+            let prefix = self.i_type.c_out()?;
+            return Ok(format!("{prefix} {} = {};\n", self.ident.name, value));
+        }
+        if let Types::Vec(element) = &self.i_type {
+            // Like Text, every Vec variable owns a copy released at the end of its scope
+            let value = value_of(&self.ident)?.c_out()?;
+            return Ok(format!(
+                "{} {} {} = {}_copy({value});\n",
+                element.vec_owner(),
+                self.i_type.c_out()?,
+                self.ident.name,
+                element.vec_helpers()
+            ));
+        }
+        if let Types::Array(element, len) = &self.i_type {
+            let value = value_of(&self.ident)?;
+            let store = array_store_c_out(&self.ident.name, element, *len, value)?;
+            return Ok(format!(
+                "{} {}[{len}];\n{store}",
+                element.c_out()?,
+                self.ident.name
+            ));
+        }
         if let Some(id_value) = self.ident.value.clone() {
             match *id_value {
                 Value::Lit(somelit) => match somelit {
                     Literal::Num(number) => {
                         let text = format!("int {} = {};\n", self.ident.name, number.val);
-                        return Some(text);
+                        return Ok(text);
                     }
-                    Literal::Text(text) => {
-                        let out = format!("char {}[] = \"{}\";\n", self.ident.name, text.value);
-                        return Some(out);
+                    Literal::Text(_) => {
+                        return Err(Diagnostic::internal(
+                            "Text declarations should have been generated already",
+                        ));
                     }
-                    Literal::Bool(inner) => {
+                    Literal::Bool(inner, _) => {
                         let valtext = {
                             match inner {
                                 BoolLiteral::True => "true",
@@ -165,106 +552,114 @@ impl DecAssignNode {
                             }
                         };
                         let text = format!("bool {} = {};\n", self.ident.name, valtext);
-                        return Some(text);
+                        return Ok(text);
                     }
                 },
                 Value::Ident(someident) => match someident.i_type {
                     Types::Number => {
                         let out = format!("int {} = {};\n", self.ident.name, someident.name);
-                        return Some(out);
+                        return Ok(out);
                     }
                     Types::String => {
-                        let out = format!(
-                            "char {}[sizeof({})];\n\
-                                strcpy({}, {});\n",
-                            self.ident.name, someident.name, self.ident.name, someident.name
-                        );
-                        return Some(out);
+                        return Err(Diagnostic::internal(
+                            "Text declarations should have been generated already",
+                        ));
                     }
                     Types::Bool => {
                         let out = format!("bool {} = {};\n", self.ident.name, someident.name);
-                        return Some(out);
-                    }
-                    Types::Function => {
-                        eprintln!("Functions as values coming soon");
-                        return None;
+                        return Ok(out);
                     }
                     Types::Nothing => {
-                        eprintln!("Oops! A nothing type should not have made it this far. Please submit an issue on github.");
-                        return None;
-                    },
-                    Types::Pointer(_) => todo!()
+                        return Err(Diagnostic::internal(
+                            "A nothing type should not have made it this far",
+                        ));
+                    }
+                    Types::Pointer(_) | Types::MutPointer(_) => {
+                        return Err(Diagnostic::internal(
+                            "Pointer declarations should have been generated already",
+                        ));
+                    }
+                    Types::Vec(_)
+                    | Types::Array(..)
+                    | Types::Struct(_)
+                    | Types::Enum(_)
+                    | Types::Function(_)
+                    | Types::Type => {
+                        return Err(Diagnostic::internal("Vec, array, struct and function declarations should have been generated already"));
+                    }
                 },
                 Value::Func(somefunc) => {
-                    let Some(funcstr) = somefunc.c_out() else {
-                        eprintln!("Delcare assign c_out upon call of function c_out");
-                        return None;
-                    };
-                    return Some(funcstr);
+                    let funcstr = somefunc.c_out()?;
+                    return Ok(funcstr);
                 }
                 Value::Call(somecall) => {
-                    let Some(mut callstr) = somecall.c_out() else {
-                        eprintln!("Declare assign c_out upon call to call c_out");
-                        return None;
-                    };
+                    let mut callstr = somecall.c_out()?;
                     let (prefix, suffix) = {
                         match somecall.func.ret {
                             Types::Bool => ("bool", ""),
-                            Types::String => ("char", "[]"),
+                            Types::String => ("char*", ""),
                             Types::Number => ("int", ""),
-                            Types::Function => todo!(),
-                            Types::Nothing => todo!(),
-                            Types::Pointer(_) => todo!(),
+                            Types::Nothing => {
+                                return Err(Diagnostic::internal(
+                                    "A call returning Nothing should have been rejected as a value",
+                                ));
+                            }
+                            Types::Pointer(_) | Types::MutPointer(_) => {
+                                return Err(Diagnostic::internal(
+                                    "Pointer declarations should have been generated already",
+                                ));
+                            }
+                            Types::Vec(_)
+                            | Types::Array(..)
+                            | Types::Struct(_)
+                            | Types::Enum(_)
+                            | Types::Function(_)
+                            | Types::Type => {
+                                return Err(Diagnostic::internal("Vec, array, struct and function declarations should have been generated already"));
+                            }
                         }
                     };
                     let out = format!("{prefix} {}{suffix} = {callstr};\n", self.ident.name);
-                    return Some(out);
+                    return Ok(out);
                 }
                 Value::Expr(someexpr) => {
-                    let Some(exprstr) = someexpr.c_out() else {
-                        eprintln!("Could not generate code for expression");
-                        return None;
-                    };
+                    let exprstr = someexpr.c_out()?;
                     let prefix = match someexpr {
                         Expression::Num(_) => "int",
                         Expression::Bool(_) => "bool",
+                        Expression::Text(_) => "char*",
                     };
-                    return Some(format!("{prefix} {} = {exprstr};\n", self.ident.name));
-                },
-                Value::Pointer(_) => { todo!(); }
+                    return Ok(format!("{prefix} {} = {exprstr};\n", self.ident.name));
+                }
+                Value::Deref(_) | Value::Index(_) | Value::Field(_) => {
+                    let value = id_value.c_out()?;
+                    let prefix = self.i_type.c_out()?;
+                    return Ok(format!("{prefix} {} = {value};\n", self.ident.name));
+                }
                 _ => {}
             }
         }
-        None
+        Err(Diagnostic::internal(format!(
+            "Could not generate the declaration of {}",
+            self.ident.name
+        )))
     }
 }
 
 impl ConditionalNode {
-    pub fn c_out(&self) -> Option<String> {
-        let upper = {
-            match &self.condition {
-                Bool::Lit(somelit) => {
-                    let cond = {
-                        if *somelit == BoolLiteral::True {
-                            "true"
-                        } else {
-                            "false"
-                        }
-                    };
-                    format!("if ({})", cond)
-                }
-                Bool::Ident(someident) => {
-                    format!("if ({})", someident.name)
-                }
-                Bool::Expr(expr) => {
-                    format!("if {}", expr.c_out()?)
-                }
-                Bool::Call(call) => {
-                    format!("if {}", call.c_out()?)
-                }
-            }
-        };
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        if matches!(self.condition, Bool::Lit(BoolLiteral::True, _)) && self.elifs.is_empty() {
+            // Folding left only the branch that always runs
+            return Ok(format!("{{\n{} }}\n", self.body.c_out()?));
+        }
+        let upper = format!("if ({})", self.condition.c_out()?);
         let body = self.body.c_out()?;
+        let mut elifs = String::new();
+        for elif in &self.elifs {
+            let elifcond = elif.condition.c_out()?;
+            let elifbody = elif.body.c_out()?;
+            elifs.push_str(&format!("else if ({elifcond}) {{\n{elifbody} }} "));
+        }
         let els_body = {
             match &self.i_else {
                 Some(block) => {
@@ -274,21 +669,66 @@ impl ConditionalNode {
                 _ => String::from(""),
             }
         };
-        let out = format!("{} {{\n{} }} {}\n", upper, body, els_body);
-        Some(out)
+        let out = format!("{} {{\n{} }} {}{}\n", upper, body, elifs, els_body);
+        Ok(out)
+    }
+}
+
+impl Bool {
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        match self {
+            Bool::Lit(somelit, span) => Literal::Bool(somelit.clone(), span.clone()).c_out(),
+            Bool::Ident(someident) => someident.c_out(),
+            Bool::Deref(someptr) => Ok(format!("(*{})", someptr.name)),
+            Bool::Index(someindex) => someindex.c_out(),
+            Bool::Field(somefield) => somefield.c_out(),
+            Bool::Expr(expr) => expr.c_out(),
+            Bool::Not(inner, _) => Ok(format!("(!{})", inner.c_out()?)),
+            Bool::Call(call) => call.c_out(),
+        }
     }
 }
 
 impl BoolExpr {
-    pub fn c_out(&self) -> Option<String> {
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
         let op = {
             match self.operator {
                 BoolOps::EQ => "==",
                 BoolOps::Lesser => "<",
+                BoolOps::NotEq => "!=",
                 BoolOps::Greater => ">",
+                BoolOps::GreaterEq => ">=",
+                BoolOps::LesserEq => "<=",
+                BoolOps::And => "&&",
+                BoolOps::Or => "||",
             }
         };
-        Some(format!(
+        if let Some(Types::Function(signature)) = self.left.get_type() {
+            // Functions are the same if they run the same code with the same environment
+            let same = format!(
+                "{}_same({}, {})",
+                signature.c_name(),
+                self.left.c_out()?,
+                self.right.c_out()?
+            );
+            return match self.operator {
+                BoolOps::NotEq => Ok(format!("(!{same})")),
+                _ => Ok(same),
+            };
+        }
+        if self.left.get_type() == Some(Types::String) {
+            // Text is compared by content rather than by address
+            let eq = format!(
+                "rob_text_eq({}, {})",
+                self.left.c_out()?,
+                self.right.c_out()?
+            );
+            return match self.operator {
+                BoolOps::NotEq => Ok(format!("(!{eq})")),
+                _ => Ok(eq),
+            };
+        }
+        Ok(format!(
             "({} {} {})",
             self.left.c_out()?,
             op,
@@ -298,101 +738,588 @@ impl BoolExpr {
 }
 
 impl Value {
-    pub fn c_out(&self) -> Option<String> {
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
         match self {
-            Value::Lit(somelit) => Some(somelit.c_out())?,
-            Value::Ident(someident) => Some(someident.name.clone()),
-            Value::Expr(expr) => Some(expr.c_out())?,
-            _ => None,
+            Value::Lit(somelit) => somelit.c_out(),
+            // A function used by its name has no environment
+            Value::Ident(someident)
+                if matches!(someident.value.as_deref(), Some(Value::Func(_))) =>
+            {
+                let Types::Function(signature) = &someident.i_type else {
+                    return Err(Diagnostic::internal(format!(
+                        "{} is a function without a function type",
+                        someident.name
+                    )));
+                };
+                Ok(format!(
+                    "(({}){{(void (*)(void)){}, NULL}})",
+                    signature.c_name(),
+                    someident.name
+                ))
+            }
+            Value::Ident(someident) => Ok(someident.name.clone()),
+            Value::Lambda(lambda) => lambda.value_c_out(),
+            Value::Expr(expr) => expr.c_out(),
+            Value::Call(call) => call.c_out(),
+            // Arrays are used through a pointer to their first element
+            Value::Deref(someptr) if matches!(someptr.i_type.pointee(), Some(Types::Array(..))) => {
+                Ok(someptr.name.clone())
+            }
+            Value::Deref(someptr) => Ok(format!("(*{})", someptr.name)),
+            Value::Pointer(target, _) if matches!(target.get_type(), Some(Types::Array(..))) => {
+                target.c_out()
+            }
+            Value::Pointer(target, _) => Ok(format!("(&{})", target.c_out()?)),
+            Value::Vec(lit) => lit.c_out(),
+            Value::Array(lit) => lit.c_out(),
+            Value::Index(someindex) => someindex.c_out(),
+            Value::Struct(lit) => lit.c_out(),
+            Value::Variant(lit) => lit.c_out(),
+            Value::Field(somefield) => somefield.c_out(),
+            _ => Err(Diagnostic::internal(
+                "Only values should be generated as C expressions",
+            )),
+        }
+    }
+}
+
+impl VecLiteral {
+    /// Builds the Vec in a statement expression. It is owned by the current block like any other
+    /// temporary.
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        let helpers = self.element.vec_helpers();
+        let mut res = format!("{}* _lit = vector_create(); ", self.element.c_out()?);
+        for element in &self.elements {
+            res.push_str(&format!("{helpers}_push(_lit, {}); ", element.c_out()?));
+        }
+        Ok(format!("{helpers}_adopt(&_rs, ({{ {res}_lit; }}))"))
+    }
+}
+
+impl ArrayLiteral {
+    /// Fills an unnamed array, which lives until the end of the current block.
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        let element = self.element.c_out()?;
+        Ok(format!(
+            "rob_array_fill_{element}(({element}[{}]){{0}}, {}, {})",
+            self.len,
+            self.len,
+            self.value.c_out()?
+        ))
+    }
+}
+
+/// Stores `value` into the array `target`. Array literals are filled in place, while other
+/// arrays are copied over, as C arrays cannot be assigned.
+fn array_store_c_out(
+    target: &String,
+    element: &Types,
+    len: usize,
+    value: &Value,
+) -> Result<String, Diagnostic> {
+    let elemtype = element.c_out()?;
+    if let Value::Array(lit) = value {
+        return Ok(format!(
+            "rob_array_fill_{elemtype}({target}, {len}, {});\n",
+            lit.value.c_out()?
+        ));
+    }
+    Ok(format!(
+        "memmove({target}, {}, sizeof({elemtype}) * {len});\n",
+        value.c_out()?
+    ))
+}
+
+impl IndexNode {
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        index_c_out(&self.vec, &self.index, self.span.line)
+    }
+}
+
+/// `vec[index]` for a Vec, an array or a pointer to an array. Constant indices into arrays were
+/// checked by typeck, every other index goes through rob_index, which aborts with the line
+/// when it is out of bounds unless the program is built with --no-bounds-checks.
+fn index_c_out(vec: &IdentifierNode, index: &Number, line: usize) -> Result<String, Diagnostic> {
+    let name = &vec.name;
+    let indexstr = index.c_out()?;
+    let len = match vec.i_type.indexed() {
+        Types::Array(..) if matches!(index, Number::Lit(_)) => {
+            return Ok(format!("{name}[{indexstr}]"));
+        }
+        Types::Array(_, len) => len.to_string(),
+        _ => format!("rob_vec_len({name})"),
+    };
+    Ok(format!("{name}[rob_index({indexstr}, {len}, {line})]"))
+}
+
+impl StructType {
+    /// The C definition of the struct. Array fields are stored in place, like array variables.
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        let mut res = format!("struct {} {{\n", self.name);
+        for (field, i_type) in &self.fields {
+            match i_type {
+                Types::Array(element, len) => {
+                    res.push_str(&format!("{} {field}[{len}];\n", element.c_out()?))
+                }
+                _ => res.push_str(&format!("{} {field};\n", i_type.c_out()?)),
+            }
+        }
+        res.push_str("};\n");
+        Ok(res)
+    }
+}
+
+impl StructLiteral {
+    /// A compound literal naming every field. Arrays cannot be initialised from other arrays in
+    /// C, so a struct with array fields is built in a statement expression and has them stored
+    /// one by one.
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        let ctype = format!("struct {}", self.structure.name);
+        let mut inits = Vec::new();
+        let mut stores = String::new();
+        for ((field, i_type), value) in self.structure.fields.iter().zip(&self.fields) {
+            match i_type {
+                Types::Array(element, len) => stores.push_str(&array_store_c_out(
+                    &format!("_lit.{field}"),
+                    element,
+                    *len,
+                    value,
+                )?),
+                _ => inits.push(format!(".{field} = {}", value.c_out()?)),
+            }
+        }
+        let inits = inits.join(", ");
+        if stores.is_empty() {
+            return Ok(format!("(({ctype}){{{inits}}})"));
+        }
+        Ok(format!("({{ {ctype} _lit = {{{inits}}}; {stores}_lit; }})"))
+    }
+}
+
+impl EnumType {
+    /// The C definition of the enum, a struct holding the tag of its variant and a union of the
+    /// fields of every variant. The fields of each variant are a struct of their own, named after
+    /// the enum and the variant like its tag.
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        let tags: Vec<String> = self
+            .variants
+            .iter()
+            .map(|variant| self.tag(&variant.name))
+            .collect();
+        let mut res = format!("enum {}_tag {{ {} }};\n", self.name, tags.join(", "));
+        let mut members = String::new();
+        for variant in &self.variants {
+            if variant.fields.is_empty() {
+                continue;
+            }
+            res.push_str(&self.payload(variant).c_out()?);
+            members.push_str(&format!(
+                "struct {} {};\n",
+                self.tag(&variant.name),
+                variant.name
+            ));
+        }
+        res.push_str(&format!(
+            "struct {} {{\nenum {}_tag tag;\n",
+            self.name, self.name
+        ));
+        if !members.is_empty() {
+            res.push_str(&format!("union {{\n{members}}} data;\n"));
+        }
+        res.push_str("};\n");
+        Ok(res)
+    }
+
+    fn tag(&self, variant: &String) -> String {
+        format!("{}_{variant}", self.name)
+    }
+
+    /// The fields of `variant` as the C struct stored in the union.
+    fn payload(&self, variant: &StructType) -> StructType {
+        StructType {
+            name: self.tag(&variant.name),
+            fields: variant.fields.clone(),
         }
     }
 }
 
+impl FuncType {
+    /// Name of the C typedef for function values with this signature. It spells out the
+    /// signature, so every use of the same signature shares one typedef.
+    pub fn c_name(&self) -> String {
+        format!("rob_func_{}", Types::Function(self.clone()).mangled())
+    }
+
+    /// A function value is a pointer to the function along with the environment of a lambda,
+    /// which is NULL for any other function. It is called through the `_call` helper, which
+    /// passes the environment on to functions that take one.
+    pub fn typedef_c_out(&self) -> Result<String, Diagnostic> {
+        let name = self.c_name();
+        let ret = self.ret.c_out()?;
+        let mut types = Vec::new();
+        let mut params = vec![format!("{name} _f")];
+        let mut args = Vec::new();
+        for (index, param) in self.params.iter().enumerate() {
+            types.push(param.c_out()?);
+            params.push(format!("{} _a{index}", param.c_out()?));
+            args.push(format!("_a{index}"));
+        }
+        let plain = if types.is_empty() {
+            String::from("void")
+        } else {
+            types.join(", ")
+        };
+        let with_env = std::iter::once(String::from("void*"))
+            .chain(types)
+            .collect::<Vec<String>>()
+            .join(", ");
+        let env_call = std::iter::once(String::from("_f.env"))
+            .chain(args.clone())
+            .collect::<Vec<String>>()
+            .join(", ");
+        let env_call = format!("(({ret} (*)({with_env}))_f.fn)({env_call})");
+        let plain_call = format!("(({ret} (*)({plain}))_f.fn)({})", args.join(", "));
+        let body = if *self.ret == Types::Nothing {
+            format!("if (_f.env != NULL) {{\n{env_call};\nreturn;\n}}\n{plain_call};\n")
+        } else {
+            format!("if (_f.env != NULL) {{\nreturn {env_call};\n}}\nreturn {plain_call};\n")
+        };
+        Ok(format!(
+            "typedef struct {{ void (*fn)(void); void* env; }} {name};\n\
+             static inline {ret} {name}_call({}) {{\n{body}}}\n\
+             static inline bool {name}_same({name} _l, {name} _r) {{\n\
+             return _l.fn == _r.fn && _l.env == _r.env;\n}}\n",
+            params.join(", ")
+        ))
+    }
+}
+
+impl LambdaNode {
+    /// The environment of the lambda, holding a copy of every variable it captured.
+    pub fn env_c_out(&self) -> Result<String, Diagnostic> {
+        let mut fields = String::new();
+        for capture in &self.captures {
+            fields.push_str(&format!("{} {};\n", capture.i_type.c_out()?, capture.name));
+        }
+        Ok(format!("struct {}_env {{\n{fields}}};\n", self.func.name))
+    }
+
+    /// The C declarator of the lifted function. A lambda that captured anything takes its
+    /// environment before its parameters.
+    pub fn signature_c_out(&self) -> Result<String, Diagnostic> {
+        if self.captures.is_empty() {
+            return self.func.signature_c_out();
+        }
+        let params = self.func.params_c_out()?;
+        let sep = if params.is_empty() { "" } else { ", " };
+        Ok(format!(
+            "{} {}(void* _env{sep}{params})",
+            self.func.ret.c_out()?,
+            self.func.name
+        ))
+    }
+
+    /// The lifted function, which starts by copying the captured variables out of its
+    /// environment under their own names.
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        let mut unpack = String::new();
+        if !self.captures.is_empty() {
+            unpack.push_str(&format!(
+                "struct {}_env* _captured = _env;\n",
+                self.func.name
+            ));
+        }
+        for capture in &self.captures {
+            unpack.push_str(&format!(
+                "{} {1} = _captured->{1};\n",
+                capture.i_type.c_out()?,
+                capture.name
+            ));
+        }
+        let body = self.func.body.c_out()?;
+        Ok(format!(
+            "{} {{\n{unpack} {} }}\n",
+            self.signature_c_out()?,
+            body
+        ))
+    }
+
+    /// The lambda as a function value. Its environment is stored in the block creating it.
+    pub fn value_c_out(&self) -> Result<String, Diagnostic> {
+        let env = if self.captures.is_empty() {
+            String::from("NULL")
+        } else {
+            let fields: Vec<String> = self
+                .captures
+                .iter()
+                .map(|capture| format!(".{0} = {0}", capture.name))
+                .collect();
+            format!("&(struct {}_env){{{}}}", self.func.name, fields.join(", "))
+        };
+        Ok(format!(
+            "(({}){{(void (*)(void)){}, {env}}})",
+            self.func.signature().c_name(),
+            self.func.name
+        ))
+    }
+}
+
+impl VariantLiteral {
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        let ctype = format!("struct {}", self.enumeration.name);
+        let tag = self.enumeration.tag(&self.variant);
+        let variant = self
+            .enumeration
+            .variant(&self.variant)
+            .ok_or_else(|| Diagnostic::internal(format!("{} has no variant", self.variant)))?;
+        if variant.fields.is_empty() {
+            return Ok(format!("(({ctype}){{.tag = {tag}}})"));
+        }
+        let payload = StructLiteral {
+            structure: self.enumeration.payload(variant),
+            fields: self.fields.clone(),
+            span: self.span.clone(),
+        };
+        Ok(format!(
+            "(({ctype}){{.tag = {tag}, .data.{} = {}}})",
+            self.variant,
+            payload.c_out()?
+        ))
+    }
+}
+
+impl MatchNode {
+    /// A switch over the tag of a copy of the matched value. Each arm copies the fields it binds
+    /// out of the union before running its body.
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        let var = format!("_match_{}", self.id);
+        let mut res = format!(
+            "{{\nstruct {} {var} = {};\nswitch ({var}.tag) {{\n",
+            self.enumeration.name,
+            self.value.c_out()?
+        );
+        for arm in &self.arms {
+            let Some(variant) = &arm.variant else {
+                // The `_` arm binds nothing
+                res.push_str(&format!(
+                    "default: {{\n{{\n{}}}\nbreak;\n}}\n",
+                    arm.body.c_out()?
+                ));
+                continue;
+            };
+            res.push_str(&format!("case {}: {{\n", self.enumeration.tag(variant)));
+            for (field, binding) in &arm.bindings {
+                res.push_str(&format!(
+                    "{} {} = {var}.data.{variant}.{field};\n",
+                    binding.i_type.c_out()?,
+                    binding.name
+                ));
+            }
+            res.push_str(&format!("{{\n{}}}\nbreak;\n}}\n", arm.body.c_out()?));
+        }
+        res.push_str("}\n}\n");
+        Ok(res)
+    }
+}
+
+impl FieldNode {
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        field_c_out(&self.target, &self.fields)
+    }
+}
+
+/// `target.a.b`, using `->` wherever the struct is reached through a pointer.
+fn field_c_out(target: &IdentifierNode, fields: &Vec<String>) -> Result<String, Diagnostic> {
+    let mut res = target.name.clone();
+    let mut current = target.i_type.clone();
+    for field in fields {
+        let access = if current.pointee().is_some() {
+            "->"
+        } else {
+            "."
+        };
+        res.push_str(&format!("{access}{field}"));
+        current = current
+            .structure()
+            .and_then(|structure| structure.field(field))
+            .ok_or_else(|| Diagnostic::internal(format!("{field} is not a field of {current}")))?
+            .clone();
+    }
+    Ok(res)
+}
+
 impl Expression {
-    pub fn c_out(&self) -> Option<String> {
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
         match self {
-            Expression::Bool(somebool) => Some(somebool.c_out()?),
-            Expression::Num(somenum) => Some(somenum.c_out()?),
+            Expression::Bool(somebool) => somebool.c_out(),
+            Expression::Num(somenum) => somenum.c_out(),
+            Expression::Text(concat) => Ok(format!(
+                "rob_text_adopt(&_rs, rob_text_concat({}, {}))",
+                concat.left.c_out()?,
+                concat.right.c_out()?
+            )),
+        }
+    }
+}
+
+impl Number {
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        match self {
+            Number::Lit(somelit) => Literal::Num(somelit.clone()).c_out(),
+            Number::Exp(somexpr) => somexpr.c_out(),
+            Number::Neg(inner, _) => Ok(format!("(-{})", inner.c_out()?)),
+            Number::Call(somecall) => somecall.c_out(),
+            Number::Ident(someident) => someident.c_out(),
+            Number::Deref(someptr) => Ok(format!("(*{})", someptr.name)),
+            Number::Index(someindex) => someindex.c_out(),
+            Number::Field(somefield) => somefield.c_out(),
+        }
+    }
+}
+
+impl Types {
+    /// Short encoding of the type for use in C names, where no two types are encoded alike.
+    /// Struct and enum names are prefixed with their length, so they can be told apart from
+    /// whatever follows them.
+    fn mangled(&self) -> String {
+        match self {
+            Types::Number => String::from("n"),
+            Types::String => String::from("t"),
+            Types::Bool => String::from("b"),
+            Types::Nothing => String::from("v"),
+            Types::Pointer(inner) => format!("p{}", inner.mangled()),
+            Types::MutPointer(inner) => format!("m{}", inner.mangled()),
+            Types::Vec(inner) => format!("V{}", inner.mangled()),
+            Types::Array(inner, len) => format!("A{len}{}", inner.mangled()),
+            Types::Struct(StructType { name, .. }) | Types::Enum(EnumType { name, .. }) => {
+                format!("{}{name}", name.len())
+            }
+            Types::Function(signature) => {
+                let params: String = signature.params.iter().map(Types::mangled).collect();
+                format!("F{params}R{}E", signature.ret.mangled())
+            }
+            Types::Type => String::from("T"),
+        }
+    }
+
+    /// The C type a value of this type is stored in.
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        match self {
+            Types::Number => Ok(String::from("int")),
+            Types::String => Ok(String::from("char*")),
+            Types::Bool => Ok(String::from("bool")),
+            Types::Nothing => Ok(String::from("void")),
+            // A pointer to an array points to its first element, which is what the array itself
+            // turns into when it is passed
+            Types::Pointer(inner) | Types::MutPointer(inner)
+                if matches!(**inner, Types::Array(..)) =>
+            {
+                inner.c_out()
+            }
+            Types::Pointer(inner)
+            | Types::MutPointer(inner)
+            | Types::Vec(inner)
+            | Types::Array(inner, _) => Ok(format!("{}*", inner.c_out()?)),
+            Types::Struct(structure) => Ok(format!("struct {}", structure.name)),
+            Types::Enum(enumeration) => Ok(format!("struct {}", enumeration.name)),
+            Types::Function(signature) => Ok(signature.c_name()),
+            Types::Type => Err(Diagnostic::internal(
+                "A type should not have made it this far",
+            )),
+        }
+    }
+
+    /// Prefix of the robvec.h helpers for a Vec with elements of this type. A Vec of Text owns
+    /// its elements, so its helpers also copy and release them.
+    fn vec_helpers(&self) -> &'static str {
+        if *self == Types::String {
+            "rob_vec_text"
+        } else {
+            "rob_vec"
+        }
+    }
+
+    /// Cleanup attribute for a variable owning a Vec with elements of this type.
+    fn vec_owner(&self) -> &'static str {
+        if *self == Types::String {
+            "ROB_OWNED_TEXT_VEC"
+        } else {
+            "ROB_OWNED_VEC"
         }
     }
 }
 
 impl NumExpression {
-    pub fn c_out(&self) -> Option<String> {
-        let left = match *(self.left.clone()) {
-            Number::Lit(somelit) => Literal::Num(somelit).c_out()?,
-            Number::Exp(somexpr) => somexpr.c_out()?,
-            Number::Call(somecall) => somecall.c_out()?,
-            Number::Ident(someident) => someident.c_out()?,
-        };
+    /// Every binary expression is wrapped in brackets, so the C output keeps the grouping the
+    /// parser decided on regardless of C's own precedence rules.
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        let left = self.left.c_out()?;
         let oper = match self.operator {
             Operators::Plus => "+",
             Operators::Minus => "-",
+            Operators::Mul => "*",
+            Operators::Div => "/",
             Operators::Mod => "%",
         };
-        let right = match *(self.right.clone()) {
-            Number::Lit(somelit) => Literal::Num(somelit).c_out()?,
-            Number::Exp(somexpr) => somexpr.c_out()?,
-            Number::Call(somecall) => somecall.c_out()?,
-            Number::Ident(someident) => someident.c_out()?,
-        };
-        Some(format!("{left} {oper} {right}"))
+        let right = self.right.c_out()?;
+        Ok(format!("({left} {oper} {right})"))
     }
 }
 
 impl Literal {
-    pub fn c_out(&self) -> Option<String> {
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
         match self {
-            Literal::Bool(somebool) => {
+            Literal::Bool(somebool, _) => {
                 if *somebool == BoolLiteral::True {
-                    Some(String::from("true"))
+                    Ok(String::from("true"))
                 } else {
-                    Some(String::from("false"))
+                    Ok(String::from("false"))
                 }
             }
-            Literal::Num(somenum) => Some(format!("{}", somenum.val)),
-            Literal::Text(sometext) => Some(sometext.value.clone()),
+            // Folding gives negative literals, which must not run into a minus before them
+            Literal::Num(somenum) if somenum.val < 0 => Ok(format!("({})", somenum.val)),
+            Literal::Num(somenum) => Ok(format!("{}", somenum.val)),
+            Literal::Text(sometext) => Ok(format!("\"{}\"", sometext.value)),
         }
     }
 }
 
 impl Function {
-    pub fn c_out(&self) -> Option<String> {
-        let fntype = {
-            match self.ret {
-                Types::Number => "int",
-                Types::String => "char*",
-                Types::Bool => "bool",
-                _ => {
-                    eprintln!("Functions as values coming soon");
-                    return None;
-                }
-            }
-        };
-        let Some(params) = self.params_c_out() else {
-            eprintln!("Params were not parsed correctly");
-            return None;
-        };
-        let Some(body) = self.body.c_out() else {
-            eprintln!("Could not emit code for function body");
-            return None;
-        };
-        let out = format!("{} {}({}) {{\n {} }}\n", fntype, self.name, params, body);
-        Some(out)
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        let signature = self.signature_c_out()?;
+        let body = self.body.c_out()?;
+        let out = format!("{} {{\n {} }}\n", signature, body);
+        Ok(out)
+    }
+
+    /// The C declarator of the function, shared by its prototype and its definition.
+    pub fn signature_c_out(&self) -> Result<String, Diagnostic> {
+        let fntype = self.ret.c_out()?;
+        let params = self.params_c_out()?;
+        Ok(format!("{} {}({})", fntype, self.name, params))
     }
 
-    fn params_c_out(&self) -> Option<String> {
+    fn params_c_out(&self) -> Result<String, Diagnostic> {
         let mut paramstr = String::new();
         let paramslen = self.params.len();
         for (index, param) in self.params.iter().enumerate() {
             let (prefix, postfix) = {
                 match param.i_type {
-                    Types::Bool => ("bool", ""),
-                    Types::String => ("char", "[]"),
-                    Types::Number => ("int", ""),
+                    Types::Bool => (String::from("bool"), ""),
+                    Types::String => (String::from("char*"), ""),
+                    Types::Number => (String::from("int"), ""),
+                    Types::Pointer(_)
+                    | Types::MutPointer(_)
+                    | Types::Vec(_)
+                    | Types::Array(..)
+                    | Types::Struct(_)
+                    | Types::Enum(_)
+                    | Types::Function(_) => (param.i_type.c_out()?, ""),
                     _ => {
-                        eprintln!("OOPS");
-                        return None;
+                        return Err(Diagnostic::global(
+                            ErrorCode::Unsupported,
+                            format!("Cannot pass a {} as a parameter yet", param.i_type),
+                        ));
                     }
                 }
             };
@@ -405,63 +1332,107 @@ impl Function {
             };
             paramstr.push_str(&varname);
         }
-        Some(paramstr)
+        Ok(paramstr)
     }
 }
 
 impl IdentifierNode {
-    pub fn arg_c_out(&self) -> Option<String> {
-        let valtxt = self.clone().value?.c_out()?;
-        Some(format!("{},", valtxt))
+    pub fn arg_c_out(&self) -> Result<String, Diagnostic> {
+        let valtxt = value_of(self)?.c_out()?;
+        Ok(format!("{},", valtxt))
     }
 
-    pub fn lit_c_out(&self) -> Option<String> {
-        match *(self.value.clone()?) {
-            Value::Lit(somelit) => match somelit {
-                Literal::Text(sometext) => Some(format!("\"{}\",", sometext.value.clone())),
-                _ => None,
-            },
-            Value::Ident(someident) => Some(someident.arg_c_out()?),
-            _ => None,
+    pub fn lit_c_out(&self) -> Result<String, Diagnostic> {
+        match value_of(self)? {
+            Value::Lit(Literal::Text(sometext)) => Ok(format!("\"{}\",", sometext.value.clone())),
+            Value::Ident(someident) => someident.arg_c_out(),
+            _ => Err(Diagnostic::global(
+                ErrorCode::Unsupported,
+                "showme only takes Text or a Text variable as its format",
+            )),
         }
     }
 
-    pub fn ptr_c_out(&self) -> Option<String> {
+    pub fn ptr_c_out(&self) -> Result<String, Diagnostic> {
         if self.i_type != Types::String {
-            return Some(format!("&({}),", self.name.clone()));
+            return Ok(format!("&({}),", self.name.clone()));
         }
-        Some(format!("{},", self.name.clone()))
+        Ok(format!("{},", self.name.clone()))
     }
 
-    pub fn c_out(&self) -> Option<String> {
-        Some(format!("{}", self.name))
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
+        Ok(format!("{}", self.name))
     }
 }
 
 impl CallNode {
-    pub fn c_out(&self) -> Option<String> {
+    pub fn c_out(&self) -> Result<String, Diagnostic> {
         match self.func.name.as_str() {
             "showme" => {
-                return Some(self.showme_c_out()?);
+                return Ok(self.showme_c_out()?);
             }
+            "rob_vec_map" | "rob_vec_filter" => return self.callback_c_out(),
             _ => {}
         }
         let mut args = String::new();
         for arg in &self.params {
             args.push_str(&arg.arg_c_out()?);
         }
-        args.remove(args.len() - 1);
-        Some(format!("{}({args})", self.func.name))
+        args.pop();
+        let call = if self.indirect {
+            let sep = if args.is_empty() { "" } else { ", " };
+            format!(
+                "{}_call({}{sep}{args})",
+                self.func.signature().c_name(),
+                self.func.name
+            )
+        } else {
+            format!("{}({args})", self.func.name)
+        };
+        if self.func.ret == Types::String {
+            // Returned Text is owned by the caller, so it is released with the current block
+            return Ok(format!("rob_text_adopt(&_rs, {call})"));
+        }
+        if let Types::Vec(element) = &self.func.ret {
+            return Ok(format!("{}_adopt(&_rs, {call})", element.vec_helpers()));
+        }
+        Ok(call)
+    }
+
+    /// `map` and `filter` loop over the Vec in a robvec.h macro, calling the function through
+    /// the call helper of its type. The new Vec is owned by the current block.
+    fn callback_c_out(&self) -> Result<String, Diagnostic> {
+        let vec = value_of(&self.params[0])?.c_out()?;
+        let callback = &self.params[1];
+        let Types::Function(signature) = &callback.i_type else {
+            return Err(Diagnostic::internal(format!(
+                "{} was given something other than a function",
+                self.func.name
+            )));
+        };
+        let func = value_of(callback)?.c_out()?;
+        let call = format!("{}_call", signature.c_name());
+        let Types::Vec(element) = &self.func.ret else {
+            return Err(Diagnostic::internal(format!(
+                "{} does not return a Vec",
+                self.func.name
+            )));
+        };
+        let res = if self.func.name == "rob_vec_map" {
+            format!("rob_vec_map({vec}, {func}, {call}, {})", element.c_out()?)
+        } else {
+            format!("{}_filter({vec}, {func}, {call})", element.vec_helpers())
+        };
+        Ok(format!("{}_adopt(&_rs, {res})", element.vec_helpers()))
     }
 
-    pub fn showme_c_out(&self) -> Option<String> {
+    pub fn showme_c_out(&self) -> Result<String, Diagnostic> {
         if self.params.len() == 0 {
-            return Some(String::from("printf();\n"));
+            return Ok(String::from("printf();\n"));
         } else if self.params.len() == 1 {
-            eprintln!("...");
             let mut temp = self.params[0].lit_c_out()?;
             temp.remove(temp.len() - 1);
-            return Some(format!("printf({});\n", temp));
+            return Ok(format!("printf({});\n", temp));
         }
 
         let mut argstr = String::new();
@@ -473,14 +1444,16 @@ impl CallNode {
                 continue;
             }
 
-            match *(arg.clone().value?) {
+            match value_of(arg)? {
                 Value::Ident(someident) => {
                     let temp = format!("{}", someident.ptr_c_out()?);
                     argstr.push_str(temp.as_str());
                 }
                 _ => {
-                    eprintln!("Can only format identifiers of the same type.");
-                    return None;
+                    return Err(Diagnostic::global(
+                        ErrorCode::Unsupported,
+                        "Can only format identifiers of the same type.",
+                    ));
                 }
             }
         }
@@ -489,11 +1462,13 @@ impl CallNode {
                 Types::Number => format!("showme_int({} INT_MAX);\n", argstr),
                 Types::String => format!("showme_text({} NULL);\n", argstr),
                 _ => {
-                    eprintln!("\n\n\nShowme is still experimental, and can only be used with Text or Number types. It is better to use an inlined printf() call.\n\n\n");
-                    return None;
+                    return Err(Diagnostic::global(
+                        ErrorCode::Unsupported,
+                        "Showme is still experimental, and can only be used with Text or Number types. It is better to use an inlined printf() call.",
+                    ));
                 }
             }
         };
-        Some(base)
+        Ok(base)
     }
 }
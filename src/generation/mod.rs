@@ -1 +1 @@
-pub mod generator;
\ No newline at end of file
+pub mod generator;
@@ -1,3 +1,7 @@
+use std::rc::Rc;
+
+use crate::diagnostics::diagnostic::Span;
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum TokenTypes {
     EXIT,
@@ -13,9 +17,11 @@ pub enum TokenTypes {
         isref: bool,
     },
     STAR,
+    SLASH,
     DOT,
     SEMI,
     LET,
+    MUT,
     QUOTE,
     PLUS,
     MINUS,
@@ -23,23 +29,35 @@ pub enum TokenTypes {
     BOOLEQ,
     GREATER,
     LESSER,
+    GREATEREQ,
+    LESSEREQ,
+    NOTEQ,
     IF,
     ELSE,
     MOD,
     OR,
     AND,
+    NOT,
     LBRACKET,
     RBRACKET,
     LCURLY,
     RCURLY,
+    LSQUARE,
+    RSQUARE,
     COLON,
     ARROW,
     NULL,
     NUMTYPE(bool),
     BOOLTYPE(bool),
     TEXTTYPE(bool),
+    VECTYPE(bool),
     FUNCTYPE,
     NOTHINGTYPE,
+    TYPETYPE,
+    STRUCT,
+    ENUM,
+    MATCH,
+    FATARROW,
     BOOL {
         val: bool,
     },
@@ -50,12 +68,18 @@ pub enum TokenTypes {
     INLINE,
     FOR,
     IN,
+    WHILE,
+    BREAK,
+    CONTINUE,
+    LABEL {
+        name: String,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub variant: TokenTypes,
-    pub line_num: usize,
+    pub span: Span,
 }
 
 impl Token {
@@ -65,6 +89,7 @@ impl Token {
             TokenTypes::IF => "IF",
             TokenTypes::AND => "AND",
             TokenTypes::OR => "OR",
+            TokenTypes::NOT => "NOT",
             TokenTypes::MOD => "MOD",
             TokenTypes::EXIT => "EXIT",
             TokenTypes::TEXT { text: _ } => "TEXT",
@@ -77,12 +102,18 @@ impl Token {
             TokenTypes::BOOLEQ => "BOOLEQ",
             TokenTypes::GREATER => "GREATER",
             TokenTypes::LESSER => "LESSER",
+            TokenTypes::GREATEREQ => "GREATEREQ",
+            TokenTypes::LESSEREQ => "LESSEREQ",
+            TokenTypes::NOTEQ => "NOTEQ",
             TokenTypes::LBRACKET => "LBRACKET",
             TokenTypes::RBRACKET => "RBRACKET",
             TokenTypes::LCURLY => "LCURLY",
             TokenTypes::RCURLY => "RCURLY",
+            TokenTypes::LSQUARE => "LSQUARE",
+            TokenTypes::RSQUARE => "RSQUARE",
             TokenTypes::NULL => "NULL",
             TokenTypes::LET => "LET",
+            TokenTypes::MUT => "MUT",
             TokenTypes::IDENT {
                 name: _,
                 isptr: _,
@@ -90,13 +121,19 @@ impl Token {
             } => "IDENT",
             TokenTypes::COLON => "COLON",
             TokenTypes::ARROW => "ARROW",
-            TokenTypes::NUMTYPE(_) => "TEXTTYPE",
+            TokenTypes::NUMTYPE(_) => "NUMTYPE",
             TokenTypes::BOOLTYPE(_) => "BOOLTYPE",
             TokenTypes::TEXTTYPE(_) => "TEXTTYPE",
+            TokenTypes::VECTYPE(_) => "VECTYPE",
             TokenTypes::FUNCTYPE => "FUNCTYPE",
             TokenTypes::BOOL { val: _ } => "BOOL",
             TokenTypes::EOF => "EOF",
             TokenTypes::NOTHINGTYPE => "NOTHINGTYPE",
+            TokenTypes::TYPETYPE => "TYPETYPE",
+            TokenTypes::STRUCT => "STRUCT",
+            TokenTypes::ENUM => "ENUM",
+            TokenTypes::MATCH => "MATCH",
+            TokenTypes::FATARROW => "FATARROW",
             TokenTypes::COMMA => "COMMA",
             TokenTypes::RETURN => "RETURN",
             TokenTypes::AMPER => "AMPER",
@@ -104,17 +141,22 @@ impl Token {
             TokenTypes::INLINE => "INLINE",
             TokenTypes::FOR => "FOR",
             TokenTypes::IN => "IN",
+            TokenTypes::WHILE => "WHILE",
+            TokenTypes::BREAK => "BREAK",
+            TokenTypes::CONTINUE => "CONTINUE",
+            TokenTypes::LABEL { name: _ } => "LABEL",
             TokenTypes::DOT => "DOT",
             TokenTypes::STAR => "STAR",
+            TokenTypes::SLASH => "SLASH",
         }
     }
 }
 
 impl Token {
-    pub fn new(tipe: TokenTypes, line: usize) -> Self {
+    pub fn new(tipe: TokenTypes, span: Span) -> Self {
         Self {
             variant: tipe,
-            line_num: line,
+            span,
         }
     }
 }
@@ -122,18 +164,49 @@ impl Token {
 pub struct FileReader {
     content: String,
     index: usize,
+    file: Rc<str>,
+    /// Byte offset at which every line starts, the first line included.
+    line_starts: Vec<usize>,
 }
 
 impl FileReader {
-    pub fn new(content: &String) -> Self {
+    pub fn new(content: &String, file: &String) -> Self {
         let content = content.to_string();
-        FileReader { content, index: 0 }
+        let line_starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(newline, _)| newline + 1))
+            .collect();
+        FileReader {
+            content,
+            index: 0,
+            file: Rc::from(file.as_str()),
+            line_starts,
+        }
     }
 
     pub fn chars(&self) -> std::str::Chars {
         self.content.chars()
     }
 
+    /// The characters of the file along with the byte offset each one starts at.
+    pub fn char_indices(&self) -> std::str::CharIndices {
+        self.content.char_indices()
+    }
+
+    /// The span of the bytes from `start` up to `end`, with the line and column `start` is on.
+    pub fn span(&self, start: usize, end: usize) -> Span {
+        let line = self
+            .line_starts
+            .partition_point(|&line_start| line_start <= start);
+        let line_start = self.line_starts[line - 1];
+        Span {
+            file: self.file.clone(),
+            line,
+            column: self.content[line_start..start].chars().count() + 1,
+            start,
+            end,
+        }
+    }
+
     pub fn peek(&self, distance: usize) -> Option<char> {
         self.content.chars().nth(self.index + distance)
     }
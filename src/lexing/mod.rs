@@ -1,2 +1,2 @@
 pub mod data;
-pub mod tokenize;
\ No newline at end of file
+pub mod tokenize;
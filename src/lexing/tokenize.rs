@@ -1,54 +1,55 @@
 use std::fs;
-use std::io;
 
 use crate::data::*;
+use crate::diagnostics::diagnostic::{Diagnostic, ErrorCode, Span};
 
-pub fn parse_start(buildfile: &String) -> Result<Option<Vec<Token>>, io::Error> {
-    let reader = fs::read_to_string(buildfile)?;
-    let filereader = FileReader::new(&reader);
-    Ok(parse_tokenize(filereader))
+pub fn parse_start(buildfile: &String) -> Result<Vec<Token>, Diagnostic> {
+    let reader = fs::read_to_string(buildfile).map_err(|err| {
+        Diagnostic::global(ErrorCode::Io, format!("Could not read {buildfile}: {err}"))
+    })?;
+    let filereader = FileReader::new(&reader, buildfile);
+    parse_tokenize(filereader)
 }
 
-pub fn parse_tokenize(reader: FileReader) -> Option<Vec<Token>> {
+pub fn parse_tokenize(reader: FileReader) -> Result<Vec<Token>, Diagnostic> {
     let mut tokens: Vec<Token> = Vec::new();
-    let mut iter = reader.chars().peekable();
+    let mut iter = reader.char_indices().peekable();
     let mut wordbuf = String::new();
+    // Byte offset of the first character of the word being buffered
+    let mut word_start = 0;
     let mut textlookup = false;
     let mut numlookup = false;
-    let mut line_number: usize = 1;
     let mut comment = false;
 
-    while let Some(current) = iter.next() {
+    while let Some((at, current)) = iter.next() {
         if comment {
             if current != '\n' {
                 continue;
             }
         }
+        if wordbuf.is_empty() {
+            word_start = at;
+        }
+        // Spans of the word buffered so far, and of the `len` bytes starting at this character
+        let word = || reader.span(word_start, at);
+        let here = |len: usize| reader.span(at, at + len);
         match current {
             '*' => {
-                if wordbuf.len() != 0 {
-                    if let Some(keyword) = test_keyword(&wordbuf, line_number) {
-                        tokens.push(keyword);
-                    } else {
-                        tokens.push(Token::new(
-                            TokenTypes::TEXT {
-                                text: wordbuf.clone(),
-                            },
-                            line_number,
-                        ));
-                    }
-                }
-                wordbuf.clear();
-                tokens.push(Token::new(TokenTypes::STAR, line_number));
+                push_word(&mut wordbuf, &mut tokens, word());
+                tokens.push(Token::new(TokenTypes::STAR, here(1)));
+            }
+            '/' => {
+                push_word(&mut wordbuf, &mut tokens, word());
+                tokens.push(Token::new(TokenTypes::SLASH, here(1)));
             }
             '.' => {
                 if wordbuf.len() == 0 {
-                    tokens.push(Token::new(TokenTypes::DOT, line_number));
+                    tokens.push(Token::new(TokenTypes::DOT, here(1)));
                     continue;
                 }
                 if let Some(num) = try_number(&wordbuf) {
-                    tokens.push(Token::new(TokenTypes::NUMBER { val: num }, line_number))
-                } else if let Some(kw) = test_keyword(&wordbuf, line_number) {
+                    tokens.push(Token::new(TokenTypes::NUMBER { val: num }, word()))
+                } else if let Some(kw) = test_keyword(&wordbuf, word()) {
                     tokens.push(kw);
                 } else {
                     let ident = TokenTypes::IDENT {
@@ -56,33 +57,20 @@ pub fn parse_tokenize(reader: FileReader) -> Option<Vec<Token>> {
                         isptr: false,
                         isref: false,
                     };
-                    tokens.push(Token::new(ident, line_number));
+                    tokens.push(Token::new(ident, word()));
                 }
                 wordbuf.clear();
-                tokens.push(Token::new(TokenTypes::DOT, line_number));
+                tokens.push(Token::new(TokenTypes::DOT, here(1)));
             }
             ',' => {
-                if wordbuf.len() == 0 {
-                    tokens.push(Token::new(TokenTypes::COMMA, line_number));
-                    continue;
-                }
-                if let Some(keyword) = test_keyword(&wordbuf, line_number) {
-                    tokens.push(keyword);
-                } else {
-                    let ident = TokenTypes::IDENT {
-                        name: wordbuf,
-                        isptr: false,
-                        isref: false,
-                    };
-                    tokens.push(Token::new(ident, line_number));
-                }
-                wordbuf = String::new();
-                tokens.push(Token::new(TokenTypes::COMMA, line_number));
+                push_word(&mut wordbuf, &mut tokens, word());
+                tokens.push(Token::new(TokenTypes::COMMA, here(1)));
             }
             '"' => {
-                'textlookup: while let Some(t) = iter.next() {
+                let mut closed = None;
+                'textlookup: while let Some((end, t)) = iter.next() {
                     if t == '\\' {
-                        if let Some(escaped) = iter.next() {
+                        if let Some((_, escaped)) = iter.next() {
                             match escaped {
                                 '"' => wordbuf.push('"'),
                                 'n' => {
@@ -98,22 +86,30 @@ pub fn parse_tokenize(reader: FileReader) -> Option<Vec<Token>> {
                         continue 'textlookup;
                     }
                     if t == '"' {
+                        closed = Some(end + 1);
                         break 'textlookup;
                     }
                     wordbuf.push(t);
                 }
-                tokens.push(Token::new(TokenTypes::TEXT { text: wordbuf }, line_number));
+                let Some(end) = closed else {
+                    return Err(Diagnostic::error(
+                        ErrorCode::UnterminatedText,
+                        &here(1),
+                        "Text literal is missing its closing '\"'",
+                    ));
+                };
+                let text = Token::new(TokenTypes::TEXT { text: wordbuf }, reader.span(at, end));
+                tokens.push(text);
                 wordbuf = String::new();
                 // tokens.push(Token::new(TokenTypes::QUOTE));
-                textlookup = !textlookup;
                 continue;
             }
             ';' => {
                 if wordbuf.len() >= 1_usize {
                     if let Some(number) = try_number(&wordbuf) {
-                        tokens.push(Token::new(TokenTypes::NUMBER { val: number }, line_number));
+                        tokens.push(Token::new(TokenTypes::NUMBER { val: number }, word()));
                         wordbuf = String::new();
-                    } else if let Some(keyword) = test_keyword(&wordbuf, line_number) {
+                    } else if let Some(keyword) = test_keyword(&wordbuf, word()) {
                         tokens.push(keyword);
                         wordbuf = String::new();
                     } else if wordbuf.len() >= 1 {
@@ -123,68 +119,81 @@ pub fn parse_tokenize(reader: FileReader) -> Option<Vec<Token>> {
                                 isptr: false,
                                 isref: false,
                             },
-                            line_number,
+                            word(),
                         );
                         tokens.push(ident);
                         wordbuf = String::new();
                     }
                 }
-                tokens.push(Token::new(TokenTypes::SEMI, line_number));
+                tokens.push(Token::new(TokenTypes::SEMI, here(1)));
                 textlookup = false;
                 numlookup = false;
                 continue;
             }
             '+' => {
-                if let Some(number) = try_number(&wordbuf) {
-                    tokens.push(Token::new(TokenTypes::NUMBER { val: number }, line_number));
-                    wordbuf = String::new();
-                }
-                tokens.push(Token::new(TokenTypes::PLUS, line_number));
+                push_word(&mut wordbuf, &mut tokens, word());
+                tokens.push(Token::new(TokenTypes::PLUS, here(1)));
             }
             '%' => {
-                if let Some(number) = try_number(&wordbuf) {
-                    tokens.push(Token::new(TokenTypes::NUMBER { val: number }, line_number));
-                    wordbuf = String::new();
-                }
-                tokens.push(Token::new(TokenTypes::MOD, line_number));
+                push_word(&mut wordbuf, &mut tokens, word());
+                tokens.push(Token::new(TokenTypes::MOD, here(1)));
             }
             '-' => {
-                if let Some(next) = iter.peek() {
+                push_word(&mut wordbuf, &mut tokens, word());
+                if let Some((_, next)) = iter.peek() {
                     match next {
                         '>' => {
                             iter.next();
-                            tokens.push(Token::new(TokenTypes::ARROW, line_number));
+                            tokens.push(Token::new(TokenTypes::ARROW, here(2)));
                         }
                         _ => {
-                            tokens.push(Token::new(TokenTypes::MINUS, line_number));
+                            tokens.push(Token::new(TokenTypes::MINUS, here(1)));
                         }
                     }
                 }
                 continue;
             }
             '<' => {
-                tokens.push(Token::new(TokenTypes::LESSER, line_number));
+                push_word(&mut wordbuf, &mut tokens, word());
+                if let Some((_, '=')) = iter.peek() {
+                    iter.next();
+                    tokens.push(Token::new(TokenTypes::LESSEREQ, here(2)));
+                } else {
+                    tokens.push(Token::new(TokenTypes::LESSER, here(1)));
+                }
             }
             '>' => {
-                tokens.push(Token::new(TokenTypes::LESSER, line_number));
+                push_word(&mut wordbuf, &mut tokens, word());
+                if let Some((_, '=')) = iter.peek() {
+                    iter.next();
+                    tokens.push(Token::new(TokenTypes::GREATEREQ, here(2)));
+                } else {
+                    tokens.push(Token::new(TokenTypes::GREATER, here(1)));
+                }
             }
             '=' => {
-                if let Some(eq) = iter.peek() {
+                push_word(&mut wordbuf, &mut tokens, word());
+                if let Some((_, eq)) = iter.peek() {
                     if *eq == '=' {
                         iter.next();
-                        tokens.push(Token::new(TokenTypes::BOOLEQ, line_number));
+                        tokens.push(Token::new(TokenTypes::BOOLEQ, here(2)));
+                        continue;
+                    }
+                    if *eq == '>' {
+                        iter.next();
+                        tokens.push(Token::new(TokenTypes::FATARROW, here(2)));
                         continue;
                     }
                 }
-                tokens.push(Token::new(TokenTypes::EQ, line_number));
+                tokens.push(Token::new(TokenTypes::EQ, here(1)));
             }
             '(' => {
                 if let Some(num) = try_number(&wordbuf) {
-                    tokens.push(Token::new(TokenTypes::NUMBER { val: num }, line_number));
-                    tokens.push(Token::new(TokenTypes::LBRACKET, line_number));
+                    tokens.push(Token::new(TokenTypes::NUMBER { val: num }, word()));
+                    tokens.push(Token::new(TokenTypes::LBRACKET, here(1)));
                     continue;
                 }
-                if let Some(keyword) = test_keyword(&wordbuf, line_number) {
+                if let Some(keyword) = test_keyword(&wordbuf, word()) {
                     tokens.push(keyword);
                     wordbuf = String::new();
                 } else if wordbuf.len() > 0 {
@@ -194,18 +203,18 @@ pub fn parse_tokenize(reader: FileReader) -> Option<Vec<Token>> {
                             isptr: false,
                             isref: false,
                         },
-                        line_number,
+                        word(),
                     );
                     wordbuf = String::new();
                     tokens.push(ident);
                 }
-                tokens.push(Token::new(TokenTypes::LBRACKET, line_number));
+                tokens.push(Token::new(TokenTypes::LBRACKET, here(1)));
             }
             ')' => {
-                if let Some(keyword) = test_keyword(&wordbuf, line_number) {
+                if let Some(keyword) = test_keyword(&wordbuf, word()) {
                     tokens.push(keyword);
                 } else if let Some(number) = try_number(&wordbuf) {
-                    tokens.push(Token::new(TokenTypes::NUMBER { val: number }, line_number));
+                    tokens.push(Token::new(TokenTypes::NUMBER { val: number }, word()));
                     numlookup = false;
                 } else if wordbuf.len() >= 1 {
                     let ident = TokenTypes::IDENT {
@@ -214,57 +223,65 @@ pub fn parse_tokenize(reader: FileReader) -> Option<Vec<Token>> {
                         isref: false,
                     };
                     wordbuf = String::new();
-                    tokens.push(Token::new(ident, line_number));
+                    tokens.push(Token::new(ident, word()));
                 }
                 wordbuf = String::new();
-                tokens.push(Token::new(TokenTypes::RBRACKET, line_number));
+                tokens.push(Token::new(TokenTypes::RBRACKET, here(1)));
                 textlookup = false;
                 numlookup = false;
                 continue;
             }
             '{' => {
-                if let Some(num) = try_number(&wordbuf) {
-                    tokens.push(Token::new(TokenTypes::NUMBER { val: num }, line_number));
-                    tokens.push(Token::new(TokenTypes::LCURLY, line_number));
-                    continue;
-                }
-                tokens.push(Token::new(TokenTypes::LCURLY, line_number));
+                push_word(&mut wordbuf, &mut tokens, word());
+                tokens.push(Token::new(TokenTypes::LCURLY, here(1)));
             }
             '}' => {
-                tokens.push(Token::new(TokenTypes::RCURLY, line_number));
+                push_word(&mut wordbuf, &mut tokens, word());
+                tokens.push(Token::new(TokenTypes::RCURLY, here(1)));
+            }
+            '[' => {
+                push_word(&mut wordbuf, &mut tokens, word());
+                tokens.push(Token::new(TokenTypes::LSQUARE, here(1)));
+            }
+            ']' => {
+                push_word(&mut wordbuf, &mut tokens, word());
+                tokens.push(Token::new(TokenTypes::RSQUARE, here(1)));
             }
             '|' => {
-                tokens.push(Token::new(TokenTypes::OR, line_number));
+                push_word(&mut wordbuf, &mut tokens, word());
+                let mut len = 1;
+                if let Some((_, '|')) = iter.peek() {
+                    iter.next();
+                    len = 2;
+                }
+                tokens.push(Token::new(TokenTypes::OR, here(len)));
             }
-            '&' => {
-                if wordbuf.len() != 0 {
-                    if let Some(keyword) = test_keyword(&wordbuf, line_number) {
-                        tokens.push(keyword)
-                    } else {
-                        tokens.push(Token::new(
-                            TokenTypes::TEXT {
-                                text: wordbuf.clone(),
-                            },
-                            line_number,
-                        ));
-                    }
+            '!' => {
+                push_word(&mut wordbuf, &mut tokens, word());
+                if let Some((_, '=')) = iter.peek() {
+                    iter.next();
+                    tokens.push(Token::new(TokenTypes::NOTEQ, here(2)));
+                } else {
+                    tokens.push(Token::new(TokenTypes::NOT, here(1)));
                 }
-                wordbuf.clear();
-                if let Some(and) = iter.peek() {
+            }
+            '&' => {
+                push_word(&mut wordbuf, &mut tokens, word());
+                if let Some((_, and)) = iter.peek() {
                     if *and == '&' {
-                        tokens.push(Token::new(TokenTypes::AND, line_number));
+                        tokens.push(Token::new(TokenTypes::AND, here(2)));
                         iter.next();
                     } else {
-                        tokens.push(Token::new(TokenTypes::AMPER, line_number));
+                        tokens.push(Token::new(TokenTypes::AMPER, here(1)));
                     }
                 }
             }
             ':' => {
                 if wordbuf.len() == 0 {
-                    tokens.push(Token::new(TokenTypes::COLON, line_number));
+                    tokens.push(Token::new(TokenTypes::COLON, here(1)));
                     continue;
                 }
-                if let Some(keyword) = test_keyword(&wordbuf, line_number) {
+                if let Some(keyword) = test_keyword(&wordbuf, word()) {
                     tokens.push(keyword);
                 } else {
                     let ident = TokenTypes::IDENT {
@@ -273,22 +290,34 @@ pub fn parse_tokenize(reader: FileReader) -> Option<Vec<Token>> {
                         isref: false,
                     };
                     wordbuf = String::new();
-                    tokens.push(Token::new(ident, line_number));
+                    tokens.push(Token::new(ident, word()));
                 }
-                tokens.push(Token::new(TokenTypes::COLON, line_number));
+                tokens.push(Token::new(TokenTypes::COLON, here(1)));
             }
             '\\' => {
-                if let Some(escaped) = iter.next() {
+                if let Some((_, escaped)) = iter.next() {
                     wordbuf.push(escaped);
                 }
             }
+            '\'' => {
+                push_word(&mut wordbuf, &mut tokens, word());
+                let mut name = String::new();
+                while let Some((_, next)) = iter.peek() {
+                    if !(next.is_alphanumeric() || *next == '_') {
+                        break;
+                    }
+                    name.push(*next);
+                    iter.next();
+                }
+                let label = here(1 + name.len());
+                tokens.push(Token::new(TokenTypes::LABEL { name }, label));
+            }
             '#' => {
                 comment = true;
                 continue;
             }
             '\n' => {
                 comment = false;
-                line_number += 1;
                 continue;
             }
             '\t' | '\r' => {}
@@ -296,12 +325,12 @@ pub fn parse_tokenize(reader: FileReader) -> Option<Vec<Token>> {
                 if textlookup {
                     wordbuf.push(current);
                 } else if current == ' ' {
-                    if let Some(keyword) = test_keyword(&wordbuf, line_number) {
+                    if let Some(keyword) = test_keyword(&wordbuf, word()) {
                         tokens.push(keyword);
                         wordbuf = String::new();
                         continue;
                     } else if let Some(num) = try_number(&wordbuf) {
-                        tokens.push(Token::new(TokenTypes::NUMBER { val: num }, line_number));
+                        tokens.push(Token::new(TokenTypes::NUMBER { val: num }, word()));
                         wordbuf.clear();
                         continue;
                     } else if wordbuf.len() >= 1 {
@@ -311,7 +340,7 @@ pub fn parse_tokenize(reader: FileReader) -> Option<Vec<Token>> {
                                 isptr: false,
                                 isref: false,
                             },
-                            line_number,
+                            word(),
                         );
                         tokens.push(ident);
                         wordbuf = String::new();
@@ -342,22 +371,41 @@ pub fn parse_tokenize(reader: FileReader) -> Option<Vec<Token>> {
                     isptr: false,
                     isref: false,
                 },
-                x.line_num,
+                x.span,
             ),
             _ => x,
         })
         .collect();
+    // A word of digits only is left as an identifier when it does not fit in a Num
+    for token in &fixed_tokens {
+        if let TokenTypes::IDENT { name, .. } = &token.variant {
+            if !name.is_empty() && name.chars().all(|c| c.is_ascii_digit()) {
+                return Err(Diagnostic::error(
+                    ErrorCode::NumberRange,
+                    &token.span,
+                    format!(
+                        "{name} does not fit in a Num, which goes up to {}",
+                        i32::MAX
+                    ),
+                ));
+            }
+        }
+    }
     let mut tokensiter = fixed_tokens.iter().peekable();
-    let mut fixedtokens2 = Vec::new();
+    let mut fixedtokens2: Vec<Token> = Vec::new();
     while let Some(next) = tokensiter.next() {
         match next.variant {
-            TokenTypes::MINUS => match tokensiter.peek()?.variant {
-                TokenTypes::NUMBER { val: previous } => {
-                    tokensiter.next();
+            // A minus directly after an operand is a subtraction, not a negative literal
+            TokenTypes::MINUS if !ends_operand(fixedtokens2.last()) => match tokensiter.peek() {
+                Some(Token {
+                    variant: TokenTypes::NUMBER { val: previous },
+                    span,
+                }) => {
                     fixedtokens2.push(Token::new(
                         TokenTypes::NUMBER { val: previous * -1 },
-                        next.line_num,
+                        next.span.to(span),
                     ));
+                    tokensiter.next();
                     continue;
                 }
                 _ => {
@@ -373,37 +421,67 @@ pub fn parse_tokenize(reader: FileReader) -> Option<Vec<Token>> {
     let mut i = 0;
     while i < fixedtokens2.len() {
         match fixedtokens2[i].variant {
-            TokenTypes::STAR => {
-                if i + 1 < fixedtokens2.len() {
-                    match fixedtokens2[i + 1].variant {
-                        TokenTypes::IDENT { name: _, isref: _, ref mut isptr } => {
+            // A star directly after an operand is a multiplication, not a pointer marker
+            TokenTypes::STAR if i == 0 || !ends_operand(Some(&fixedtokens2[i - 1])) => {
+                // In `*mut Num` the mut stays in front of the marked type
+                let target = match fixedtokens2.get(i + 1) {
+                    Some(Token {
+                        variant: TokenTypes::MUT,
+                        ..
+                    }) => i + 2,
+                    _ => i + 1,
+                };
+                if target < fixedtokens2.len() {
+                    let marked = match fixedtokens2[target].variant {
+                        TokenTypes::IDENT {
+                            name: _,
+                            isref: _,
+                            ref mut isptr,
+                        } => {
                             *isptr = true;
+                            true
                         }
                         TokenTypes::TEXTTYPE(ref mut isptr) => {
                             *isptr = true;
+                            true
                         }
                         TokenTypes::BOOLTYPE(ref mut isptr) => {
                             *isptr = true;
+                            true
                         }
                         TokenTypes::NUMTYPE(ref mut isptr) => {
                             *isptr = true;
+                            true
                         }
-                        _ => {continue;}
+                        TokenTypes::VECTYPE(ref mut isptr) => {
+                            *isptr = true;
+                            true
+                        }
+                        _ => false,
+                    };
+                    if marked {
+                        // The marked token takes over the span of the star
+                        fixedtokens2[target].span =
+                            fixedtokens2[i].span.to(&fixedtokens2[target].span);
+                        fixedtokens2.remove(i);
+                        continue;
                     }
-                    fixedtokens2.remove(i);
-                    continue;
                 }
             }
             TokenTypes::AMPER => {
                 if i + 1 < fixedtokens2.len() {
-                    match fixedtokens2[i + 1].variant {
-                        TokenTypes::IDENT { name: _, isptr: _, ref mut isref } => {
-                            *isref = true;
-                        }
-                        _ => {continue;}
+                    if let TokenTypes::IDENT {
+                        name: _,
+                        isptr: _,
+                        ref mut isref,
+                    } = fixedtokens2[i + 1].variant
+                    {
+                        *isref = true;
+                        fixedtokens2[i + 1].span =
+                            fixedtokens2[i].span.to(&fixedtokens2[i + 1].span);
+                        fixedtokens2.remove(i);
+                        continue;
                     }
-                    fixedtokens2.remove(i);
-                    continue;
                 }
             }
             _ => {}
@@ -411,56 +489,71 @@ pub fn parse_tokenize(reader: FileReader) -> Option<Vec<Token>> {
         i += 1;
     }
 
-    Some(fixedtokens2)
+    Ok(fixedtokens2)
 }
 
-pub fn test_keyword(word: &String, line: usize) -> Option<Token> {
+pub fn test_keyword(word: &String, span: Span) -> Option<Token> {
     match word.as_str() {
-        "exit" => Some(Token {
-            variant: TokenTypes::EXIT,
-            line_num: line,
-        }),
-        "let" => Some(Token {
-            variant: TokenTypes::LET,
-            line_num: line,
-        }),
-        "Num" => Some(Token {
-            variant: TokenTypes::NUMTYPE(false),
-            line_num: line,
-        }),
-        "Text" => Some(Token {
-            variant: TokenTypes::TEXTTYPE(false),
-            line_num: line,
-        }),
-        "Bool" => Some(Token {
-            variant: TokenTypes::BOOLTYPE(false),
-            line_num: line,
-        }),
-        "Func" => Some(Token {
-            variant: TokenTypes::FUNCTYPE,
-            line_num: line,
-        }),
-        "True" => Some(Token {
-            variant: TokenTypes::BOOL { val: true },
-            line_num: line,
-        }),
-        "False" => Some(Token {
-            variant: TokenTypes::BOOL { val: false },
-            line_num: line,
-        }),
-        "return" => Some(Token {
-            variant: TokenTypes::RETURN,
-            line_num: line,
-        }),
-        "if" => Some(Token::new(TokenTypes::IF, line)),
-        "else" => Some(Token::new(TokenTypes::ELSE, line)),
-        "inline" => Some(Token::new(TokenTypes::INLINE, line)),
-        "for" => Some(Token::new(TokenTypes::FOR, line)),
-        "in" => Some(Token::new(TokenTypes::IN, line)),
+        "exit" => Some(Token::new(TokenTypes::EXIT, span)),
+        "let" => Some(Token::new(TokenTypes::LET, span)),
+        "mut" => Some(Token::new(TokenTypes::MUT, span)),
+        "Num" => Some(Token::new(TokenTypes::NUMTYPE(false), span)),
+        "Text" => Some(Token::new(TokenTypes::TEXTTYPE(false), span)),
+        "Bool" => Some(Token::new(TokenTypes::BOOLTYPE(false), span)),
+        "Vec" => Some(Token::new(TokenTypes::VECTYPE(false), span)),
+        "Func" => Some(Token::new(TokenTypes::FUNCTYPE, span)),
+        "Nothing" => Some(Token::new(TokenTypes::NOTHINGTYPE, span)),
+        "Type" => Some(Token::new(TokenTypes::TYPETYPE, span)),
+        "struct" => Some(Token::new(TokenTypes::STRUCT, span)),
+        "enum" => Some(Token::new(TokenTypes::ENUM, span)),
+        "match" => Some(Token::new(TokenTypes::MATCH, span)),
+        "True" => Some(Token::new(TokenTypes::BOOL { val: true }, span)),
+        "False" => Some(Token::new(TokenTypes::BOOL { val: false }, span)),
+        "return" => Some(Token::new(TokenTypes::RETURN, span)),
+        "if" => Some(Token::new(TokenTypes::IF, span)),
+        "else" => Some(Token::new(TokenTypes::ELSE, span)),
+        "inline" => Some(Token::new(TokenTypes::INLINE, span)),
+        "for" => Some(Token::new(TokenTypes::FOR, span)),
+        "in" => Some(Token::new(TokenTypes::IN, span)),
+        "while" => Some(Token::new(TokenTypes::WHILE, span)),
+        "break" => Some(Token::new(TokenTypes::BREAK, span)),
+        "continue" => Some(Token::new(TokenTypes::CONTINUE, span)),
         _ => None,
     }
 }
 
+/// Pushes whatever word is buffered as a number, keyword or identifier token.
+fn push_word(wordbuf: &mut String, tokens: &mut Vec<Token>, span: Span) {
+    if wordbuf.trim().is_empty() {
+        wordbuf.clear();
+        return;
+    }
+    if let Some(num) = try_number(wordbuf) {
+        tokens.push(Token::new(TokenTypes::NUMBER { val: num }, span));
+    } else if let Some(keyword) = test_keyword(wordbuf, span.clone()) {
+        tokens.push(keyword);
+    } else {
+        let ident = TokenTypes::IDENT {
+            name: wordbuf.clone(),
+            isptr: false,
+            isref: false,
+        };
+        tokens.push(Token::new(ident, span));
+    }
+    wordbuf.clear();
+}
+
+/// Whether a token can end an operand, making a following '-' or '*' a binary operator.
+fn ends_operand(token: Option<&Token>) -> bool {
+    match token.map(|t| &t.variant) {
+        Some(TokenTypes::IDENT { .. })
+        | Some(TokenTypes::NUMBER { .. })
+        | Some(TokenTypes::RBRACKET)
+        | Some(TokenTypes::RSQUARE) => true,
+        _ => false,
+    }
+}
+
 pub fn try_number(word: &String) -> Option<i32> {
     for character in word.chars() {
         if !character.is_numeric() {
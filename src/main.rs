@@ -2,10 +2,14 @@
 
 use clap::Parser;
 
+use crate::analysis::{assignment, escape, fold, typeck};
+use crate::diagnostics::diagnostic::Diagnostic;
 use crate::generation::generator;
 use crate::lexing::*;
 use crate::parsing::*;
 
+mod analysis;
+mod diagnostics;
 mod generation;
 mod lexing;
 mod parsing;
@@ -21,6 +25,10 @@ struct Args {
     /// Roblang source file to compile
     #[arg(short, long)]
     file: String,
+
+    /// Leave out the runtime checks on indices into Vecs and arrays
+    #[arg(long)]
+    no_bounds_checks: bool,
 }
 
 fn main() {
@@ -33,7 +41,14 @@ fn main() {
         tokens = true;
     }
     let buildfile = args.file;
-    let _ = build(&buildfile, tokens, nodes);
+    // The stages only hand their diagnostics back, so this is where they are reported, and a
+    // failed build exits with 1 so tools can tell
+    if let Err(diagnostics) = build(&buildfile, tokens, nodes, !args.no_bounds_checks) {
+        for diagnostic in diagnostics {
+            eprintln!("{diagnostic}");
+        }
+        std::process::exit(1);
+    }
     if run {
         let mut namechars = buildfile.chars().peekable();
         let mut newname = String::new();
@@ -50,33 +65,32 @@ fn main() {
     }
 }
 
-fn build(file: &String, tokensshow: bool, nodes: bool) -> Option<()> {
+fn build(
+    file: &String,
+    tokensshow: bool,
+    nodes: bool,
+    bounds_checks: bool,
+) -> Result<(), Vec<Diagnostic>> {
     let robstd = std::env::var("ROBSTD").unwrap_or_default();
     let cvec = std::env::var("CVEC").unwrap_or_default();
-    let tokens = tokenize::parse_start(&file).expect("weird")?;
+    let tokens = tokenize::parse_start(&file)?;
     if tokensshow {
         for i in tokens.clone() {
             println!("{:?}", i);
         }
     }
     let mut parser = parsetree::ParseTree::new(&tokens);
-    let mut root = parser.parse();
-    if root.check_top().is_none() {
-        return None;
-    }
+    let syntax = parser.parse()?;
+    let mut root = typeck::check(&syntax)?;
+    assignment::check(&root)?;
+    escape::check(&root)?;
+    fold::fold(&mut root)?;
     if nodes {
         nodes::print_program(&root.children, 0);
     }
-    let gen = generator::Generator::new(root.clone());
-    let writeres = gen.write();
-    match writeres {
-        Err(_) => {
-            eprintln!("generation failed");
-        }
-        Ok(_) => {
-            eprintln!("Build success!");
-        }
-    }
+    let gen = generator::Generator::new(root, file, bounds_checks);
+    gen.write()?;
+    eprintln!("Build success!");
     let dotpos = {
         let mut ind = 0;
         for (index, item) in file.chars().enumerate() {
@@ -99,5 +113,5 @@ fn build(file: &String, tokensshow: bool, nodes: bool) -> Option<()> {
         .spawn()
         .expect("could not run");
 
-    Some(())
+    Ok(())
 }
@@ -1,2 +1,3 @@
+pub mod nodes;
 pub mod parsetree;
-pub mod nodes;
\ No newline at end of file
+pub mod syntax;
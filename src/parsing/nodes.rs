@@ -1,5 +1,8 @@
 use std::collections::HashMap;
 
+use crate::diagnostics::diagnostic::{Diagnostic, ErrorCode, Span};
+use crate::lexing::data::TokenTypes;
+
 #[derive(Debug)]
 pub struct SymbolTable {
     table: HashMap<String, IdentifierNode>,
@@ -51,6 +54,11 @@ impl SymbolStack {
         self.stack.last_mut()
     }
 
+    /// Number of scopes, 1 while the top level of the program is parsed.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
     pub fn search_down(&self, name: &String) -> Option<IdentifierNode> {
         for table in self.stack.iter().rev() {
             if let Some(ident) = table.get(name) {
@@ -59,45 +67,34 @@ impl SymbolStack {
         }
         None
     }
+
+    /// Like `search_down`, but also gives the index of the scope the name was found in, 0 being
+    /// the top level.
+    pub fn search_depth(&self, name: &String) -> Option<(usize, IdentifierNode)> {
+        for (depth, table) in self.stack.iter().enumerate().rev() {
+            if let Some(ident) = table.get(name) {
+                return Some((depth, ident.clone()));
+            }
+        }
+        None
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Program {
     pub children: Vec<StatementNode>,
+    /// Every function type written in the program, each of which gets a C typedef.
+    pub func_types: Vec<FuncType>,
+    /// Every lambda in the program, each of which is lifted to a top level C function.
+    pub lambdas: Vec<LambdaNode>,
 }
 
 impl Program {
     pub fn new() -> Self {
         Program {
             children: Vec::new(),
-        }
-    }
-
-    pub fn check_top(&self) -> Option<()> {
-        let mut maincheck = false;
-        for child in &self.children {
-            match child {
-                StatementNode::DeclareAssign(node) => {
-                    if node.i_type == Types::Function {
-                        if node.ident.name == "main".to_string() {
-                            maincheck = true;
-                        }
-                        continue;
-                    }
-                    eprintln!("Top level code coming soon");
-                    return None;
-                }
-                _ => {
-                    eprintln!("Top level code coming soon");
-                    return None;
-                }
-            }
-        }
-        if maincheck {
-            Some(())
-        } else {
-            eprintln!("No main function found");
-            None
+            func_types: Vec::new(),
+            lambdas: Vec::new(),
         }
     }
 }
@@ -112,47 +109,159 @@ pub enum StatementNode {
     Return(ReturnNode),
     Inline(InlineC),
     ForLoop(ForNode),
+    WhileLoop(WhileNode),
+    Break(LoopControl),
+    Continue(LoopControl),
+    Match(MatchNode),
 }
 
 #[derive(Debug, Clone)]
 pub struct ForNode {
     pub dec: IdentifierNode,
-    pub range: RangeNode,
+    pub iter: Iterable,
+    pub body: BlockNode,
+    pub label: Option<LoopLabel>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct WhileNode {
+    pub condition: Bool,
+    pub body: BlockNode,
+    pub label: Option<LoopLabel>,
+    pub span: Span,
+}
+
+/// `match value { Variant { field, field: name } => { ... } _ => { ... } }`, running the arm of
+/// the variant the enum holds. Every variant has an arm, unless there is a `_` arm.
+#[derive(Debug, Clone)]
+pub struct MatchNode {
+    pub value: Value,
+    pub enumeration: EnumType,
+    pub arms: Vec<MatchArm>,
+    /// Unique per program, naming the C variable the value is kept in.
+    pub id: usize,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    /// None for the `_` arm.
+    pub variant: Option<String>,
+    /// Every field bound by the arm, with the binding it is copied into.
+    pub bindings: Vec<(String, IdentifierNode)>,
     pub body: BlockNode,
+    pub span: Span,
+}
+
+/// Label of a loop. `id` is unique per program, as C has no labelled break and continue and
+/// jumps to generated labels instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoopLabel {
+    pub name: String,
+    pub id: usize,
+}
+
+/// A `break` or `continue`, applying to the innermost loop unless labelled.
+#[derive(Debug, Clone)]
+pub struct LoopControl {
+    pub label: Option<LoopLabel>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct RangeNode {
     pub start: Number,
     pub end: Number,
+    pub span: Span,
+}
+
+/// What a for loop goes over.
+#[derive(Debug, Clone)]
+pub enum Iterable {
+    Range(RangeNode),
+    /// Every element of a Vec or array, in order.
+    Elements(Value),
 }
 
 #[derive(Debug, Clone)]
-pub struct InlineC(pub String);
+pub struct InlineC {
+    pub code: String,
+    pub span: Span,
+}
 
 #[derive(Debug, Clone)]
 pub enum Number {
     Lit(NumLiteral),
     Exp(Box<NumExpression>),
+    /// Negation, with the span of the whole `-value`.
+    Neg(Box<Number>, Span),
     Ident(IdentifierNode),
+    Deref(IdentifierNode),
+    Index(IndexNode),
+    Field(FieldNode),
     Call(CallNode),
 }
 
+impl Number {
+    /// Turns a value that was checked to be a Num back into a number node.
+    pub fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Lit(Literal::Num(somelit)) => Some(Number::Lit(somelit)),
+            Value::Ident(someident) => Some(Number::Ident(someident)),
+            Value::Deref(someptr) => Some(Number::Deref(someptr)),
+            Value::Index(someindex) => Some(Number::Index(someindex)),
+            Value::Field(somefield) => Some(Number::Field(somefield)),
+            Value::Call(somecall) => Some(Number::Call(somecall)),
+            Value::Expr(Expression::Num(somenum)) => Some(somenum),
+            _ => None,
+        }
+    }
+
+    pub fn to_value(self) -> Value {
+        match self {
+            Number::Lit(somelit) => Value::Lit(Literal::Num(somelit)),
+            Number::Ident(someident) => Value::Ident(someident),
+            Number::Deref(someptr) => Value::Deref(someptr),
+            Number::Index(someindex) => Value::Index(someindex),
+            Number::Field(somefield) => Value::Field(somefield),
+            Number::Call(somecall) => Value::Call(somecall),
+            expr => Value::Expr(Expression::Num(expr)),
+        }
+    }
+
+    pub fn span(&self) -> &Span {
+        match self {
+            Number::Lit(lit) => &lit.span,
+            Number::Exp(expr) => &expr.span,
+            Number::Neg(_, span) => span,
+            Number::Ident(ident) | Number::Deref(ident) => &ident.span,
+            Number::Index(index) => &index.span,
+            Number::Field(field) => &field.span,
+            Number::Call(call) => &call.span,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NumExpression {
     pub left: Box<Number>,
     pub operator: Operators,
     pub right: Box<Number>,
+    pub span: Span,
 }
 
 impl NumExpression {
+    /// `left operator right`, spanning from the start of `left` to the end of `right`.
     pub fn new(left: Number, operator: Operators, right: Number) -> Self {
+        let span = left.span().to(right.span());
         let left = Box::new(left);
         let right = Box::new(right);
         NumExpression {
             left,
             operator,
             right,
+            span,
         }
     }
 }
@@ -161,12 +270,28 @@ impl NumExpression {
 pub enum Operators {
     Plus,
     Minus,
+    Mul,
+    Div,
     Mod,
 }
 
+impl Operators {
+    pub fn from_token(token: &TokenTypes) -> Option<Self> {
+        match token {
+            TokenTypes::PLUS => Some(Operators::Plus),
+            TokenTypes::MINUS => Some(Operators::Minus),
+            TokenTypes::STAR => Some(Operators::Mul),
+            TokenTypes::SLASH => Some(Operators::Div),
+            TokenTypes::MOD => Some(Operators::Mod),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NumLiteral {
     pub val: i32,
+    pub span: Span,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -174,14 +299,160 @@ pub enum Types {
     Number,
     String,
     Bool,
-    Function,
+    Function(FuncType),
     Pointer(Box<Types>),
+    /// Pointer to a mutable value, which can be written through.
+    MutPointer(Box<Types>),
+    /// Growable array backed by c-vector.
+    Vec(Box<Types>),
+    /// Array with a length fixed at compile time, stored in place.
+    Array(Box<Types>, usize),
+    Struct(StructType),
+    Enum(EnumType),
+    /// Type of a name bound to a type, like `Point` in `let Point: Type = struct { ... };`.
+    Type,
     Nothing,
 }
 
+/// Signature of a function, written as `Func(Num, Num) -> Bool`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FuncType {
+    pub params: Vec<Types>,
+    pub ret: Box<Types>,
+}
+
+/// Tagged union declared with `enum { ... }`. Each variant holds its fields like a struct
+/// named after the variant.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EnumType {
+    pub name: String,
+    pub variants: Vec<StructType>,
+}
+
+impl EnumType {
+    pub fn variant(&self, name: &String) -> Option<&StructType> {
+        self.variants.iter().find(|variant| variant.name == *name)
+    }
+}
+
+/// Record type declared with `struct { ... }`. Two struct types are the same if they have the
+/// same name.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StructType {
+    pub name: String,
+    /// Name and type of every field, in the order they were declared.
+    pub fields: Vec<(String, Types)>,
+}
+
+impl StructType {
+    pub fn field(&self, name: &String) -> Option<&Types> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, i_type)| i_type)
+    }
+}
+
+impl Types {
+    /// The type behind a pointer, if this is one.
+    pub fn pointee(&self) -> Option<&Types> {
+        match self {
+            Types::Pointer(inner) | Types::MutPointer(inner) => Some(inner),
+            _ => None,
+        }
+    }
+
+    /// The type of the elements, if this is a Vec or an array.
+    pub fn element(&self) -> Option<&Types> {
+        match self {
+            Types::Vec(inner) | Types::Array(inner, _) => Some(inner),
+            _ => None,
+        }
+    }
+
+    /// The type indexing a value of this type works on. A pointer to an array is indexed like
+    /// the array itself.
+    pub fn indexed(&self) -> &Types {
+        match self.pointee() {
+            Some(array @ Types::Array(..)) => array,
+            _ => self,
+        }
+    }
+
+    /// The struct whose fields `.` reaches on a value of this type. Fields are read through a
+    /// pointer to a struct like on the struct itself.
+    pub fn structure(&self) -> Option<&StructType> {
+        match self.pointee().unwrap_or(self) {
+            Types::Struct(structure) => Some(structure),
+            _ => None,
+        }
+    }
+
+    /// Whether a value of this type may refer to other memory, which is the case for pointers,
+    /// structs with a field holding one and functions, which may be lambdas referring to what
+    /// they captured.
+    pub fn holds_pointers(&self) -> bool {
+        match self {
+            Types::Pointer(_) | Types::MutPointer(_) | Types::Function(_) => true,
+            Types::Struct(structure) => structure
+                .fields
+                .iter()
+                .any(|(_, i_type)| i_type.holds_pointers()),
+            Types::Enum(enumeration) => enumeration.variants.iter().any(|variant| {
+                variant
+                    .fields
+                    .iter()
+                    .any(|(_, i_type)| i_type.holds_pointers())
+            }),
+            _ => false,
+        }
+    }
+
+    /// Whether a value of type `found` can be used where this type is expected. A pointer to a
+    /// mutable value can always be used as a read only one, and an empty Vec literal, whose
+    /// elements are Nothing, can be used as any Vec.
+    pub fn accepts(&self, found: &Types) -> bool {
+        match (self, found) {
+            (Types::Pointer(expected), Types::MutPointer(inner)) => **expected == **inner,
+            (Types::Vec(_), Types::Vec(inner)) if **inner == Types::Nothing => true,
+            _ => self == found,
+        }
+    }
+}
+
+impl std::fmt::Display for Types {
+    /// Formats the type the way it is written in roblang source.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Types::Number => write!(f, "Num"),
+            Types::String => write!(f, "Text"),
+            Types::Bool => write!(f, "Bool"),
+            Types::Function(signature) => {
+                let params: Vec<String> = signature
+                    .params
+                    .iter()
+                    .map(|param| param.to_string())
+                    .collect();
+                write!(f, "Func({}) -> {}", params.join(", "), signature.ret)
+            }
+            Types::Pointer(inner) => write!(f, "*{inner}"),
+            Types::MutPointer(inner) => write!(f, "*mut {inner}"),
+            Types::Vec(inner) => write!(f, "Vec[{inner}]"),
+            Types::Array(inner, len) => write!(f, "[{inner}; {len}]"),
+            Types::Struct(structure) => write!(f, "{}", structure.name),
+            Types::Enum(enumeration) => write!(f, "{}", enumeration.name),
+            Types::Type => write!(f, "Type"),
+            Types::Nothing => write!(f, "Nothing"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BlockNode {
     pub children: Vec<StatementNode>,
+    /// From the first statement to the end of the last, or the span of what the block belongs
+    /// to when it is empty.
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -189,10 +460,14 @@ pub struct IdentifierNode {
     pub name: String,
     pub i_type: Types,
     pub value: Option<Box<Value>>,
+    pub mutable: bool,
+    /// Where the name was declared, the default span for builtins and values that were never
+    /// declared.
+    pub declared: Span,
+    /// Where this use of the name is. The same as `declared` on a declaration.
+    pub span: Span,
 }
 
-struct ParseError;
-
 impl IdentifierNode {
     pub fn new(name: &String, i_type: &Types, val: Value) -> Self {
         let i_type = i_type.clone();
@@ -201,6 +476,9 @@ impl IdentifierNode {
             name: name.clone(),
             i_type,
             value: Some(valbox),
+            mutable: false,
+            declared: Span::default(),
+            span: Span::default(),
         }
     }
 
@@ -209,29 +487,52 @@ impl IdentifierNode {
             i_type: IdentifierNode::get_type_from(val.clone())?,
             value: Some(Box::new(val.clone())),
             name: String::from(""),
+            mutable: false,
+            declared: Span::default(),
+            span: val.span().cloned().unwrap_or_default(),
         })
     }
 
+    /// Marks this as the declaration at `span`.
+    pub fn declare_at(&mut self, span: &Span) {
+        self.declared = span.clone();
+        self.span = span.clone();
+    }
+
     fn get_type_from(val: Value) -> Option<Types> {
         let f_type = match &val {
             Value::Ident(node) => node.i_type.clone(),
             Value::Lit(lit) => match lit {
                 Literal::Num(_) => Types::Number,
-                Literal::Bool(_) => Types::Bool,
+                Literal::Bool(..) => Types::Bool,
                 Literal::Text(_) => Types::String,
             },
             Value::Expr(expr) => match expr {
                 Expression::Num(_) => Types::Number,
                 Expression::Bool(_) => Types::Bool,
+                Expression::Text(_) => Types::String,
             },
             Value::Nothing => {
                 return None;
             }
-            Value::Func(_) => Types::Function,
+            Value::Func(func) => Types::Function(func.signature()),
+            Value::Lambda(lambda) => Types::Function(lambda.func.signature()),
             Value::Call(node) => node.func.ret.clone(),
-            Value::Pointer(boxedval) => Types::Pointer(Box::new(IdentifierNode::get_type_from(
-                (**boxedval).clone(),
-            )?)),
+            Value::Pointer(boxedval, _) => {
+                let inner = Box::new(IdentifierNode::get_type_from((**boxedval).clone())?);
+                match boxedval.as_ref() {
+                    Value::Ident(target) if target.mutable => Types::MutPointer(inner),
+                    _ => Types::Pointer(inner),
+                }
+            }
+            Value::Deref(node) => node.i_type.pointee()?.clone(),
+            Value::Vec(lit) => Types::Vec(Box::new(lit.element.clone())),
+            Value::Array(lit) => Types::Array(Box::new(lit.element.clone()), lit.len),
+            Value::Index(node) => node.vec.i_type.indexed().element()?.clone(),
+            Value::Struct(lit) => Types::Struct(lit.structure.clone()),
+            Value::Variant(lit) => Types::Enum(lit.enumeration.clone()),
+            Value::Field(node) => node.i_type.clone(),
+            Value::Type(_) => Types::Type,
         };
         Some(f_type)
     }
@@ -243,18 +544,55 @@ pub enum Value {
     Expr(Expression),
     Ident(IdentifierNode),
     Func(Function),
+    Lambda(LambdaNode),
     Call(CallNode),
-    Pointer(Box<Value>),
+    /// `&name`, with the span of the whole reference.
+    Pointer(Box<Value>, Span),
+    /// Read through a pointer, holding the pointer identifier.
+    Deref(IdentifierNode),
+    Vec(VecLiteral),
+    Array(ArrayLiteral),
+    Index(IndexNode),
+    Struct(StructLiteral),
+    Variant(VariantLiteral),
+    Field(FieldNode),
+    /// The type a type name stands for, only held by the symbol table entry of that name.
+    Type(Types),
     Nothing,
 }
 
 impl Value {
+    pub fn get_type(&self) -> Option<Types> {
+        IdentifierNode::get_type_from(self.clone())
+    }
+
     pub fn is_nothing(&self) -> bool {
         match self {
             Value::Nothing => true,
             _ => false,
         }
     }
+
+    /// None for a type and for Nothing, which are not written anywhere.
+    pub fn span(&self) -> Option<&Span> {
+        let span = match self {
+            Value::Lit(lit) => lit.span(),
+            Value::Expr(expr) => expr.span(),
+            Value::Ident(ident) | Value::Deref(ident) => &ident.span,
+            Value::Func(func) => &func.span,
+            Value::Lambda(lambda) => &lambda.span,
+            Value::Call(call) => &call.span,
+            Value::Pointer(_, span) => span,
+            Value::Vec(lit) => &lit.span,
+            Value::Array(lit) => &lit.span,
+            Value::Index(index) => &index.span,
+            Value::Struct(lit) => &lit.span,
+            Value::Variant(lit) => &lit.span,
+            Value::Field(field) => &field.span,
+            Value::Type(_) | Value::Nothing => return None,
+        };
+        Some(span)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -263,59 +601,236 @@ pub struct Function {
     pub params: Vec<IdentifierNode>,
     pub ret: Types,
     pub body: BlockNode,
+    pub span: Span,
+}
+
+impl Function {
+    pub fn signature(&self) -> FuncType {
+        FuncType {
+            params: self
+                .params
+                .iter()
+                .map(|param| param.i_type.clone())
+                .collect(),
+            ret: Box::new(self.ret.clone()),
+        }
+    }
+}
+
+/// `(x: Num) -> Num { ... }` used as a value. The lambda gets a copy of every local variable of
+/// the enclosing functions it uses, which it keeps in an environment next to its function.
+#[derive(Debug, Clone)]
+pub struct LambdaNode {
+    /// The body, named after the C function it is lifted to.
+    pub func: Function,
+    /// Every captured variable, as it was declared outside of the lambda.
+    pub captures: Vec<IdentifierNode>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub enum Expression {
-    Num(NumExpression),
-    Bool(BoolExpr),
+    Num(Number),
+    Bool(Bool),
+    Text(TextExpr),
+}
+
+impl Expression {
+    pub fn span(&self) -> &Span {
+        match self {
+            Expression::Num(num) => num.span(),
+            Expression::Bool(cond) => cond.span(),
+            Expression::Text(text) => &text.span,
+        }
+    }
+}
+
+/// Concatenation of two Text values with `+`.
+#[derive(Debug, Clone)]
+pub struct TextExpr {
+    pub left: Box<Value>,
+    pub right: Box<Value>,
+    pub span: Span,
+}
+
+/// `[a, b, c]`, creating a new Vec.
+#[derive(Debug, Clone)]
+pub struct VecLiteral {
+    pub elements: Vec<Value>,
+    pub element: Types,
+    pub span: Span,
+}
+
+/// `[value; len]`, an array holding `len` copies of `value`.
+#[derive(Debug, Clone)]
+pub struct ArrayLiteral {
+    pub value: Box<Value>,
+    pub element: Types,
+    pub len: usize,
+    pub span: Span,
+}
+
+/// Read of the element at `index` of the Vec or array `vec`, which may also be a pointer to an
+/// array.
+#[derive(Debug, Clone)]
+pub struct IndexNode {
+    pub vec: IdentifierNode,
+    pub index: Box<Number>,
+    /// Reported by its line when the index is out of bounds at runtime.
+    pub span: Span,
+}
+
+/// `Point { x: 1, y: 2 }`, creating a struct. The fields may be given in any order, but are
+/// stored in the order the struct declares them.
+#[derive(Debug, Clone)]
+pub struct StructLiteral {
+    pub structure: StructType,
+    pub fields: Vec<Value>,
+    pub span: Span,
+}
+
+/// `Shape.Circle { radius: 1 }`, creating an enum holding the variant `variant`. The fields
+/// are stored in the order the variant declares them.
+#[derive(Debug, Clone)]
+pub struct VariantLiteral {
+    pub enumeration: EnumType,
+    pub variant: String,
+    pub fields: Vec<Value>,
+    pub span: Span,
+}
+
+/// Read of a field of the struct `target`, or of the struct it points to. Each name in
+/// `fields` goes one struct deeper, so `line.start.x` has the fields `start` and `x`.
+#[derive(Debug, Clone)]
+pub struct FieldNode {
+    pub target: IdentifierNode,
+    pub fields: Vec<String>,
+    /// Type of the last field.
+    pub i_type: Types,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub enum Literal {
     Num(NumLiteral),
-    Bool(BoolLiteral),
+    Bool(BoolLiteral, Span),
     Text(TextLit),
 }
 
+impl Literal {
+    pub fn span(&self) -> &Span {
+        match self {
+            Literal::Num(lit) => &lit.span,
+            Literal::Bool(_, span) => span,
+            Literal::Text(lit) => &lit.span,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AssignNode {
     pub ident: IdentifierNode,
+    /// Writes to the value `ident` points to rather than to `ident` itself.
+    pub deref: bool,
+    /// Writes to this element of the Vec or array `ident` rather than to `ident` itself.
+    pub index: Option<Number>,
+    /// Writes to the field reached through these names rather than to `ident` itself.
+    pub fields: Vec<String>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct DecAssignNode {
     pub ident: IdentifierNode,
     pub i_type: Types,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct DeclareNode {
     pub ident: IdentifierNode,
     pub i_type: Types,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct CallNode {
     pub func: Function,
     pub params: Vec<IdentifierNode>,
+    /// Calls through a variable holding a function, which may be a lambda, rather than calling
+    /// a function by its name.
+    pub indirect: bool,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct ConditionalNode {
     pub condition: Bool,
     pub body: BlockNode,
+    pub elifs: Vec<ElseIfNode>,
     pub i_else: Option<BlockNode>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct ElseIfNode {
+    pub condition: Bool,
+    pub body: BlockNode,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub enum Bool {
-    Lit(BoolLiteral),
+    Lit(BoolLiteral, Span),
     Expr(BoolExpr),
+    /// Negation, with the span of the whole `!value`.
+    Not(Box<Bool>, Span),
     Ident(IdentifierNode),
+    Deref(IdentifierNode),
+    Index(IndexNode),
+    Field(FieldNode),
     Call(CallNode),
 }
 
+impl Bool {
+    /// Turns a value that was checked to be a Bool back into a bool node.
+    pub fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Lit(Literal::Bool(lit, span)) => Some(Bool::Lit(lit, span)),
+            Value::Ident(ident) => Some(Bool::Ident(ident)),
+            Value::Deref(ptr) => Some(Bool::Deref(ptr)),
+            Value::Index(index) => Some(Bool::Index(index)),
+            Value::Field(field) => Some(Bool::Field(field)),
+            Value::Call(call) => Some(Bool::Call(call)),
+            Value::Expr(Expression::Bool(somebool)) => Some(somebool),
+            _ => None,
+        }
+    }
+
+    pub fn to_value(self) -> Value {
+        match self {
+            Bool::Lit(lit, span) => Value::Lit(Literal::Bool(lit, span)),
+            Bool::Ident(ident) => Value::Ident(ident),
+            Bool::Deref(ptr) => Value::Deref(ptr),
+            Bool::Index(index) => Value::Index(index),
+            Bool::Field(field) => Value::Field(field),
+            Bool::Call(call) => Value::Call(call),
+            expr => Value::Expr(Expression::Bool(expr)),
+        }
+    }
+
+    pub fn span(&self) -> &Span {
+        match self {
+            Bool::Lit(_, span) | Bool::Not(_, span) => span,
+            Bool::Expr(expr) => &expr.span,
+            Bool::Ident(ident) | Bool::Deref(ident) => &ident.span,
+            Bool::Index(index) => &index.span,
+            Bool::Field(field) => &field.span,
+            Bool::Call(call) => &call.span,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum BoolLiteral {
     True,
@@ -327,28 +842,66 @@ pub struct BoolExpr {
     pub left: Box<Value>,
     pub operator: BoolOps,
     pub right: Box<Value>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub enum BoolOps {
     EQ,
+    NotEq,
     Greater,
     Lesser,
+    GreaterEq,
+    LesserEq,
+    And,
+    Or,
+}
+
+impl BoolOps {
+    pub fn from_token(token: &TokenTypes) -> Option<Self> {
+        match token {
+            TokenTypes::BOOLEQ => Some(BoolOps::EQ),
+            TokenTypes::GREATER => Some(BoolOps::Greater),
+            TokenTypes::LESSER => Some(BoolOps::Lesser),
+            TokenTypes::NOTEQ => Some(BoolOps::NotEq),
+            TokenTypes::GREATEREQ => Some(BoolOps::GreaterEq),
+            TokenTypes::LESSEREQ => Some(BoolOps::LesserEq),
+            TokenTypes::AND => Some(BoolOps::And),
+            TokenTypes::OR => Some(BoolOps::Or),
+            _ => None,
+        }
+    }
+
+    /// Whether the operator combines two Bools rather than comparing two values.
+    pub fn is_logical(&self) -> bool {
+        matches!(self, BoolOps::And | BoolOps::Or)
+    }
+
+    /// Whether the operator orders its operands, which only makes sense for Nums.
+    pub fn is_ordering(&self) -> bool {
+        matches!(
+            self,
+            BoolOps::Greater | BoolOps::Lesser | BoolOps::GreaterEq | BoolOps::LesserEq
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ReturnNode {
     pub value: Value,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct NumExpressionNode {
     pub value: i32,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct TextLit {
     pub value: String,
+    pub span: Span,
 }
 
 pub fn print_tree(node: &StatementNode, indent: usize) {
@@ -356,31 +909,33 @@ pub fn print_tree(node: &StatementNode, indent: usize) {
     match node {
         StatementNode::Assign(node) => println!("{}Assign: {:?}", indentation, node),
         StatementNode::Declare(node) => println!("{}Declare: {:?}", indentation, node),
-        StatementNode::DeclareAssign(node) => match node.ident.i_type {
-            Types::Function => {
-                let Some(inner) = &node.ident.value else {
-                    todo!()
-                };
-                match inner.as_ref() {
-                    Value::Func(funcnode) => {
-                        let children = &funcnode.body.children;
-                        println!(
-                            "Declare function {} with params {:?} returns {:?}",
-                            node.ident.name, funcnode.params, funcnode.ret
-                        );
-                        print_program(children, indent + 1);
-                    }
-                    _ => {}
-                }
-            }
-            _ => {
-                println!("{}DeclareAssign: {:?}", indentation, node)
+        StatementNode::DeclareAssign(node) => match node.ident.value.as_deref() {
+            Some(Value::Func(funcnode)) => {
+                let children = &funcnode.body.children;
+                println!(
+                    "Declare function {} with params {:?} returns {:?}",
+                    node.ident.name, funcnode.params, funcnode.ret
+                );
+                print_program(children, indent + 1);
             }
+            // Shows the type even where it was inferred rather than written
+            _ => println!(
+                "{}DeclareAssign {}: {} = {:?}",
+                indentation, node.ident.name, node.i_type, node.ident.value
+            ),
         },
         StatementNode::Call(node) => println!("{}Call: {:?}", indentation, node),
         StatementNode::Conditional(node) => {
-            println!("{}Conditional: {:?}", indentation, node);
+            println!("{}Conditional: {:?}", indentation, node.condition);
             print_program(&node.body.children, indent + 1);
+            for elif in &node.elifs {
+                println!("{}Else if: {:?}", indentation, elif.condition);
+                print_program(&elif.body.children, indent + 1);
+            }
+            if let Some(els) = &node.i_else {
+                println!("{}Else:", indentation);
+                print_program(&els.children, indent + 1);
+            }
         }
         StatementNode::Return(node) => println!("{}Return: {:?}", indentation, node),
         StatementNode::Inline(inline) => println!("{}Inline: {:?}", indentation, inline),
@@ -388,6 +943,20 @@ pub fn print_tree(node: &StatementNode, indent: usize) {
             println!("{}For: {:?}", indentation, node);
             print_program(&node.body.children, indent + 1)
         }
+        StatementNode::WhileLoop(node) => {
+            println!("{}While: {:?}", indentation, node);
+            print_program(&node.body.children, indent + 1)
+        }
+        StatementNode::Break(node) => println!("{}Break: {:?}", indentation, node),
+        StatementNode::Continue(node) => println!("{}Continue: {:?}", indentation, node),
+        StatementNode::Match(node) => {
+            println!("{}Match: {:?}", indentation, node.value);
+            for arm in &node.arms {
+                let variant = arm.variant.as_deref().unwrap_or("_");
+                println!("{}Arm {}: {:?}", indentation, variant, arm.bindings);
+                print_program(&arm.body.children, indent + 1);
+            }
+        }
     }
 }
 
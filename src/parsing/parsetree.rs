@@ -1,15 +1,26 @@
 use std::iter::Peekable;
 use std::slice::Iter;
 
+use crate::diagnostics::diagnostic::{Diagnostic, ErrorCode, Span};
 use crate::lexing::data::*;
-use crate::parsing::nodes::*;
+use crate::parsing::nodes::{BoolOps, Operators};
+use crate::parsing::syntax::*;
 
+/// Builds the syntax tree of a program from its tokens. Only the shape of the program is checked
+/// here, what the names stand for and whether the types fit is left to `analysis::typeck`.
 #[derive(Debug)]
 pub struct ParseTree<'a> {
     pub index: usize,
     pub iter: Peekable<Iter<'a, Token>>,
-    pub root: Program,
-    pub symbols: SymbolStack,
+    tokens: &'a Vec<Token>,
+    /// Set while parsing an expression followed by a block, such as the condition of an if,
+    /// where `name { }` is a name followed by an empty block rather than an empty literal.
+    before_block: bool,
+}
+
+/// A syntax error at `span`.
+fn syntax(span: &Span, message: impl std::fmt::Display) -> Diagnostic {
+    Diagnostic::error(ErrorCode::Syntax, span, message)
 }
 
 impl<'a> ParseTree<'a> {
@@ -17,833 +28,926 @@ impl<'a> ParseTree<'a> {
         ParseTree {
             index: 0_usize,
             iter: tokens.iter().peekable(),
-            root: Program::new(),
-            symbols: SymbolStack::new(),
+            tokens,
+            before_block: false,
         }
     }
 
-    pub fn parse(&mut self) -> &Program {
-        let mut children = Vec::new();
-        if let Some(masterblock) = self.parse_until(TokenTypes::EOF, None, None) {
-            children = masterblock.children.clone();
+    /// The span from `start` to the end of the last token consumed, which is that of the node
+    /// starting at `start` once it is parsed.
+    fn since(&self, start: &Span) -> Span {
+        let consumed = self.tokens.len() - self.iter.len();
+        match consumed.checked_sub(1) {
+            Some(last) => start.to(&self.tokens[last].span),
+            None => start.clone(),
         }
-        self.root.children = children;
-        &self.root
     }
 
-    pub fn parse_until(
-        &mut self,
-        end_token: TokenTypes,
-        params: Option<Vec<IdentifierNode>>,
-        rettype: Option<Types>,
-    ) -> Option<BlockNode> {
-        let mut basetbl = SymbolTable::new();
-        if let Some(params) = params {
-            for param in params {
-                basetbl.insert(param.name.clone(), param.clone());
-            }
-        }
-        let mut newblock = BlockNode {
-            children: Vec::new(),
-        };
-        let mocktkn = Token::new(end_token.clone(), 0);
-        let eof = end_token == TokenTypes::EOF;
-
-        // MANUALLY INSTERTING STD FUNCTIONS, WILL FIX LATER
-
-        if eof {
-            let showme = Function {
-                name: String::from("showme"),
-                body: BlockNode {
-                    children: Vec::new(),
-                },
-                ret: Types::Nothing,
-                params: Vec::new(),
-            };
-            let showmeident = IdentifierNode {
-                name: String::from("showme"),
-                i_type: Types::Function,
-                value: Some(Box::new(Value::Func(showme))),
-            };
-            basetbl.insert(String::from("showme"), showmeident);
-
-            let efailure = IdentifierNode {
-                name: String::from("EXIT_FAILURE"),
-                value: Some(Box::from(Value::Lit(Literal::Num(NumLiteral { val: 1 })))),
-                i_type: Types::Number,
-            };
-            basetbl.insert(String::from("EXIT_FAILURE"), efailure);
-
-            let esucc = IdentifierNode {
-                name: String::from("EXIT_SUCCESS"),
-                value: Some(Box::from(Value::Lit(Literal::Num(NumLiteral { val: 0 })))),
-                i_type: Types::Number,
-            };
-            basetbl.insert(String::from("EXIT_SUCCESS"), esucc);
-        }
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Diagnostic> {
+        self.parse_until(TokenTypes::EOF)
+    }
 
-        self.symbols.push(basetbl);
+    /// Parses statements up to `end_token`, which has to come before the end of the file unless
+    /// it is EOF.
+    pub fn parse_until(&mut self, end_token: TokenTypes) -> Result<Vec<Stmt>, Diagnostic> {
+        let mut children = Vec::new();
+        let eof = end_token == TokenTypes::EOF;
         'mainloop: while let Some(current) = self.iter.next() {
-            if !eof && (current.variant_name() == mocktkn.variant_name()) {
-                break 'mainloop;
+            if !eof && current.variant == end_token {
+                return Ok(children);
             }
+            let start = &current.span;
             match &current.variant {
                 TokenTypes::LET => {
-                    if let Some(node) = self.parse_declare_assign() {
-                        let stmt = StatementNode::DeclareAssign(node);
-                        newblock.children.push(stmt);
-                    }
+                    let stmt = self.parse_let(start)?;
+                    children.push(Stmt::Let(stmt));
                 }
                 TokenTypes::RETURN => {
-                    if rettype.is_none() {
-                        eprintln!("line {}: Cannot return from global scope", current.line_num);
-                        return None;
-                    }
-                    let targtype = rettype.clone().unwrap();
-                    if let Some(value) = self.parse_return() {
-                        match value.value {
-                            Value::Ident(ref inner) => {
-                                if inner.i_type != targtype {
-                                    eprintln!("Line {}: Mismatched return type.", current.line_num);
-                                    return None;
-                                }
-                            }
-                            Value::Nothing => {
-                                if targtype != Types::Nothing {
-                                    eprintln!(
-                                        "Line {}: Mismatched return type, found Nothing",
-                                        current.line_num
-                                    );
-                                    return None;
-                                }
-                            }
-                            _ => {}
-                        }
-                        let stmt = StatementNode::Return(value.clone());
-                        newblock.children.push(stmt);
-                    }
+                    let stmt = self.parse_return(start)?;
+                    children.push(Stmt::Return(stmt));
                 }
                 TokenTypes::IF => {
-                    let Some(cond) = self.parse_conditional(rettype.clone()?) else {
-                        eprintln!("Line {}: Failed to parse conditional", current.line_num);
-                        return None;
-                    };
-                    let stmt = StatementNode::Conditional(cond);
-                    newblock.children.push(stmt);
+                    let stmt = self.parse_conditional(start)?;
+                    children.push(Stmt::If(stmt));
                 }
                 TokenTypes::INLINE => {
-                    let Some(inlinenode) = self.parse_inline() else {
-                        eprintln!("Line {}: Failed to parse inline", current.line_num);
-                        return None;
-                    };
-                    let stmt = StatementNode::Inline(inlinenode);
-                    newblock.children.push(stmt);
+                    let code = self.parse_inline(start)?;
+                    self.expect_semi(start, "inline C")?;
+                    children.push(Stmt::Inline(InlineStmt {
+                        code,
+                        span: self.since(start),
+                    }));
                 }
                 TokenTypes::IDENT {
                     name,
                     isref: _,
-                    isptr: _,
+                    isptr,
                 } => {
+                    if let Some(eq) = self.iter.peek() {
+                        if eq.variant == TokenTypes::EQ {
+                            let stmt = self.parse_assign(name, start, *isptr)?;
+                            children.push(Stmt::Assign(stmt));
+                            continue 'mainloop;
+                        }
+                        if eq.variant == TokenTypes::LSQUARE {
+                            let stmt = self.parse_index_assign(name, start)?;
+                            children.push(Stmt::Assign(stmt));
+                            continue 'mainloop;
+                        }
+                        if eq.variant == TokenTypes::DOT {
+                            let stmt = self.parse_field_assign(name, start)?;
+                            children.push(Stmt::Assign(stmt));
+                            continue 'mainloop;
+                        }
+                    }
                     let Some(lbrac) = self.iter.next() else {
-                        eprintln!("Line {}: Expected '(' during call", current.line_num);
-                        return None;
+                        return Err(syntax(start, "Expected '(' during call"));
                     };
                     if lbrac.variant != TokenTypes::LBRACKET {
-                        eprintln!(
-                            "Line {}: Expected '(' during function call",
-                            current.line_num
-                        );
-                        let mockfunc = Function {
-                            params: Vec::new(),
-                            name: String::from(""),
-                            body: BlockNode {
-                                children: Vec::new(),
-                            },
-                            ret: Types::Nothing,
-                        };
-                        let _ = self.parse_call(mockfunc);
-                        return None;
-                    }
-                    let Some(func) = self.symbols.search_down(name) else {
-                        eprintln!(
-                            "Line {}: No identifier {} found in current scope",
-                            current.line_num, name
-                        );
-                        return None;
-                    };
-                    match func.value {
-                        Some(mybox) => match *mybox {
-                            Value::Func(myfunc) => {
-                                let callnode = self.parse_call(myfunc)?;
-                                newblock.children.push(StatementNode::Call(callnode));
-                            }
-                            _ => {
-                                eprintln!(
-                                    "Line {}: Identifier {} is not a function",
-                                    current.line_num, func.name
-                                );
-                                return None;
-                            }
-                        },
-                        None => panic!(),
+                        return Err(syntax(start, "Expected '(' during function call"));
                     }
+                    let call = self.parse_call(name, start)?;
+                    self.expect_semi(start, "call")?;
+                    children.push(Stmt::Call(call));
                 }
                 TokenTypes::FOR => {
-                    let forstmt = self.parse_for(rettype.clone()?)?;
-                    newblock.children.push(StatementNode::ForLoop(forstmt));
+                    let stmt = self.parse_for(None, start)?;
+                    children.push(Stmt::For(stmt));
+                }
+                TokenTypes::WHILE => {
+                    let stmt = self.parse_while(None, start)?;
+                    children.push(Stmt::While(stmt));
+                }
+                TokenTypes::LABEL { name } => {
+                    let stmt = self.parse_labelled_loop(name, start)?;
+                    children.push(stmt);
+                }
+                TokenTypes::BREAK => {
+                    let stmt = self.parse_loop_control("break", start)?;
+                    children.push(Stmt::Break(stmt));
+                }
+                TokenTypes::CONTINUE => {
+                    let stmt = self.parse_loop_control("continue", start)?;
+                    children.push(Stmt::Continue(stmt));
+                }
+                TokenTypes::MATCH => {
+                    let stmt = self.parse_match(start)?;
+                    children.push(Stmt::Match(stmt));
+                }
+                _ => {
+                    return Err(syntax(
+                        start,
+                        format!("Expected a statement, found {}", current.variant_name()),
+                    ));
                 }
-                _ => {}
             }
         }
-        self.symbols.pop();
-        Some(newblock)
+        if !eof {
+            let last = self.tokens.last().map(|token| token.span.clone());
+            return Err(syntax(
+                &last.unwrap_or_default(),
+                format!("Expected {end_token:?}, found EOF"),
+            ));
+        }
+        Ok(children)
+    }
+
+    /// The next token, which the program cannot end before. `expected` says what should come
+    /// next, and `start` is the span of the token the construct being parsed starts with.
+    fn next_token(&mut self, expected: &str, start: &Span) -> Result<&'a Token, Diagnostic> {
+        self.iter
+            .next()
+            .ok_or_else(|| syntax(start, format!("Expected {expected}, found EOF")))
     }
 
-    fn parse_inline(&mut self) -> Option<InlineC> {
-        let next = self.iter.next()?;
+    /// Consumes the next token, which has to be `target` as named by `Token::variant_name`.
+    fn expect(&mut self, target: &str, start: &Span) -> Result<&'a Token, Diagnostic> {
+        let token = self.next_token(target, start)?;
+        if token.variant_name() != target {
+            return Err(syntax(
+                &token.span,
+                format!("Expected {target}, but found {}", token.variant_name()),
+            ));
+        }
+        Ok(token)
+    }
+
+    fn parse_inline(&mut self, start: &Span) -> Result<String, Diagnostic> {
+        let next = self.next_token("the C code as TEXT after inline", start)?;
         match &next.variant {
-            TokenTypes::TEXT { text } => Some(InlineC(text.clone())),
-            _ => None,
+            TokenTypes::TEXT { text } => Ok(text.clone()),
+            _ => Err(syntax(
+                &next.span,
+                format!(
+                    "Expected the C code as TEXT after inline, found {}",
+                    next.variant_name()
+                ),
+            )),
         }
     }
 
-    fn parse_return(&mut self) -> Option<ReturnNode> {
-        if let Some(ident) = self.iter.next() {
-            match &ident.variant {
-                TokenTypes::IDENT {
-                    name,
-                    isptr: _,
-                    isref: _,
-                } => {
-                    if let Some(identnode) = self.symbols.search_down(name) {
-                        let retnode = ReturnNode {
-                            value: Value::Ident(identnode.clone()),
-                        };
-                        if let Some(semi) = self.iter.peek() {
-                            if semi.variant_name() == "SEMI" {
-                                self.iter.next();
-                                return Some(retnode);
-                            } else {
-                                eprintln!("Line {}: Expected SEMI", semi.line_num);
-                                return None;
-                            }
-                        } else {
-                            eprintln!("Line {}: Expected SEMI, found EOF", ident.line_num);
-                            return None;
-                        }
-                    } else {
-                        eprintln!(
-                            "Line {}: No identifier {name} found in current scope",
-                            ident.line_num
-                        );
-                        return None;
-                    }
-                }
-                TokenTypes::SEMI => {
-                    return Some(ReturnNode {
-                        value: Value::Nothing,
-                    });
-                }
-                _ => {
-                    eprintln!("Line {}: A function must either return an identifier or nothing. assign the value you want to return to a variable, then return that variable.", ident.line_num);
-                    return None;
-                }
-            }
+    /// Parses the value after `return`, if there is one.
+    fn parse_return(&mut self, start: &Span) -> Result<ReturnStmt, Diagnostic> {
+        let Some(next) = self.iter.peek() else {
+            return Err(syntax(start, "Expected a return value or ';', found EOF"));
+        };
+        if next.variant == TokenTypes::SEMI {
+            self.iter.next();
+            return Ok(ReturnStmt {
+                value: None,
+                span: self.since(start),
+            });
+        }
+        let value = self.parse_expression(0)?;
+        self.expect_semi(start, "return value")?;
+        Ok(ReturnStmt {
+            value: Some(value),
+            span: self.since(start),
+        })
+    }
+
+    /// Consumes the ';' ending a statement, whose last part is described by `after`.
+    fn expect_semi(&mut self, start: &Span, after: &str) -> Result<(), Diagnostic> {
+        let semi = self.next_token("SEMI", start)?;
+        if semi.variant != TokenTypes::SEMI {
+            return Err(syntax(
+                &semi.span,
+                format!("Expected SEMI after {after}, found {}", semi.variant_name()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Parses `name = value;`, or `*name = value;` when `deref` is set.
+    fn parse_assign(
+        &mut self,
+        name: &String,
+        start: &Span,
+        deref: bool,
+    ) -> Result<AssignStmt, Diagnostic> {
+        self.iter.next();
+        let value = self.parse_expression(0)?;
+        self.expect_semi(start, "assignment")?;
+        let target = if deref {
+            Place::Deref(name.clone())
         } else {
-            eprintln!("Expected IDENT, found EOF");
-            return None;
+            Place::Name(name.clone())
+        };
+        Ok(AssignStmt {
+            target,
+            value,
+            span: self.since(start),
+        })
+    }
+
+    /// Parses `name[index] = value;`.
+    fn parse_index_assign(
+        &mut self,
+        name: &String,
+        start: &Span,
+    ) -> Result<AssignStmt, Diagnostic> {
+        let index = self.parse_subscript(start)?;
+        self.expect("EQ", start)?;
+        let value = self.parse_expression(0)?;
+        self.expect("SEMI", start)?;
+        Ok(AssignStmt {
+            target: Place::Index(name.clone(), index),
+            value,
+            span: self.since(start),
+        })
+    }
+
+    /// Parses `name.field = value;`, which may go through several fields.
+    fn parse_field_assign(
+        &mut self,
+        name: &String,
+        start: &Span,
+    ) -> Result<AssignStmt, Diagnostic> {
+        let fields = self.parse_field_path(start)?;
+        self.expect("EQ", start)?;
+        let value = self.parse_expression(0)?;
+        self.expect("SEMI", start)?;
+        Ok(AssignStmt {
+            target: Place::Field(name.clone(), fields),
+            value,
+            span: self.since(start),
+        })
+    }
+
+    /// Parses `.field` as many times as it follows.
+    fn parse_field_path(&mut self, start: &Span) -> Result<Vec<String>, Diagnostic> {
+        let mut fields = Vec::new();
+        while self.next_is_field() {
+            self.iter.next();
+            let Some(Token {
+                variant: TokenTypes::IDENT { name, .. },
+                ..
+            }) = self.iter.next()
+            else {
+                return Err(syntax(start, "Expected a field name after '.'"));
+            };
+            fields.push(name.clone());
         }
+        Ok(fields)
     }
 
-    fn parse_declare_assign(&mut self) -> Option<DecAssignNode> {
-        let mut name: String = String::new();
-        let mut value: Value = Value::Nothing;
-        let mut i_type: Types;
-        if let Some(next) = self.iter.next() {
-            match &next.variant {
-                TokenTypes::IDENT {
-                    name: nombre,
-                    isref: _,
-                    isptr: _,
-                } => {
-                    name = nombre.clone();
-                }
-                _ => {
-                    eprintln!(
-                        "Line {}: Expected identifier after let but found {}",
-                        next.line_num,
-                        next.variant_name()
-                    );
-                    return None;
-                }
+    /// Whether the next tokens are `.name`. A '.' followed by anything else may be part of a
+    /// range.
+    fn next_is_field(&mut self) -> bool {
+        let mut lookahead = self.iter.clone();
+        lookahead
+            .next()
+            .is_some_and(|dot| dot.variant == TokenTypes::DOT)
+            && lookahead
+                .next()
+                .is_some_and(|name| matches!(name.variant, TokenTypes::IDENT { .. }))
+    }
+
+    /// Whether the next tokens are `{ name:`, which starts the fields of a struct or variant
+    /// literal rather than a block, or `{ }` where no block can follow.
+    fn next_is_literal(&mut self) -> bool {
+        let mut lookahead = self.iter.clone();
+        if !lookahead
+            .next()
+            .is_some_and(|lcurly| lcurly.variant == TokenTypes::LCURLY)
+        {
+            return false;
+        }
+        match lookahead.next().map(|token| &token.variant) {
+            Some(TokenTypes::IDENT { .. }) => lookahead
+                .next()
+                .is_some_and(|colon| colon.variant == TokenTypes::COLON),
+            Some(TokenTypes::RCURLY) => !self.before_block,
+            _ => false,
+        }
+    }
+
+    /// Parses the expression in front of the block of an if, loop or match.
+    fn parse_head(&mut self) -> Result<Expr, Diagnostic> {
+        let outer = std::mem::replace(&mut self.before_block, true);
+        let head = self.parse_expression(0);
+        self.before_block = outer;
+        head
+    }
+
+    /// Parses an expression nested in brackets, parentheses or a literal, which no block follows
+    /// even inside the head of an if, loop or match.
+    fn parse_inner(&mut self) -> Result<Expr, Diagnostic> {
+        let outer = std::mem::replace(&mut self.before_block, false);
+        let inner = self.parse_expression(0);
+        self.before_block = outer;
+        inner
+    }
+
+    /// Parses a `let`, which declares a binding, a function or a type.
+    fn parse_let(&mut self, start: &Span) -> Result<LetStmt, Diagnostic> {
+        let name: String;
+        let mut mutable = false;
+        if let Some(next) = self.iter.peek() {
+            if next.variant == TokenTypes::MUT {
+                mutable = true;
+                self.iter.next();
             }
         }
-        if let Some(col) = self.iter.next() {
-            if col.variant_name() != "COLON" {
-                eprintln!("Line {}: Expected ':' after declaration", col.line_num);
-                return None;
+        let next = self.next_token("identifier after let", start)?;
+        match &next.variant {
+            TokenTypes::IDENT {
+                name: nombre,
+                isref: _,
+                isptr: _,
+            } => {
+                name = nombre.clone();
             }
-        } else {
-            eprintln!("EOF after LET");
-            return None;
+            _ => {
+                return Err(syntax(
+                    &next.span,
+                    format!(
+                        "Expected identifier after let but found {}",
+                        next.variant_name()
+                    ),
+                ));
+            }
+        }
+        let stmt = |kind: LetKind, span: Span| LetStmt {
+            name: name.clone(),
+            mutable,
+            kind,
+            span,
+        };
+        let col = self.next_token("':' or '=' after declaration", start)?;
+        if col.variant == TokenTypes::EQ {
+            let value = self.parse_expression(0)?;
+            self.expect_semi(start, "declaration")?;
+            return Ok(stmt(LetKind::Value(None, value), self.since(start)));
+        }
+        if col.variant_name() != "COLON" {
+            return Err(syntax(&col.span, "Expected ':' or '=' after declaration"));
         }
 
-        if let Some(typetkn) = self.iter.next() {
-            match &typetkn.variant {
-                TokenTypes::NUMTYPE(isptr) => {
-                    i_type = {
-                        if *isptr {
-                            Types::Pointer(Box::new(Types::Number))
-                        } else {
-                            Types::Number
-                        }
-                    };
-                }
-                TokenTypes::TEXTTYPE(isptr) => {
-                    i_type = {
-                        if *isptr {
-                            Types::Pointer(Box::new(Types::String))
-                        } else {
-                            Types::String
-                        }
-                    };
-                }
-                TokenTypes::BOOLTYPE(isptr) => {
-                    i_type = {
-                        if *isptr {
-                            Types::Pointer(Box::new(Types::Bool))
-                        } else {
-                            Types::Bool
-                        }
-                    };
-                }
-                TokenTypes::FUNCTYPE => {
-                    i_type = Types::Function;
-                }
-                _ => {
-                    eprintln!(
-                        "Line {}: Expected TYPE during declaration",
-                        typetkn.line_num
-                    );
-                    return None;
-                }
-            }
-        } else {
-            eprintln!("Expected TYPE, found EOF");
-            return None;
+        if self
+            .iter
+            .peek()
+            .is_some_and(|token| token.variant == TokenTypes::TYPETYPE)
+        {
+            self.iter.next();
+            self.expect("EQ", start)?;
+            let definition = self.parse_type_definition(&name, start)?;
+            return Ok(stmt(LetKind::Type(definition), self.since(start)));
         }
 
-        if let Some(eqtoken) = self.iter.next() {
-            if eqtoken.variant_name() != "EQ" {
-                eprintln!(
-                    "Line {}: Expected EQ, got {}",
-                    eqtoken.line_num,
-                    eqtoken.variant_name()
-                );
-                return None;
+        // `let name: Func = (params) -> Type { ... }` defines a function, typed by its signature
+        let mut lookahead = self.iter.clone();
+        let defines = lookahead
+            .next()
+            .is_some_and(|token| token.variant == TokenTypes::FUNCTYPE)
+            && lookahead
+                .next()
+                .is_some_and(|token| token.variant == TokenTypes::EQ);
+        if defines {
+            self.iter = lookahead;
+            if !self
+                .iter
+                .peek()
+                .is_some_and(|token| token.variant == TokenTypes::LBRACKET)
+            {
+                return Err(syntax(
+                    start,
+                    "Expected a function definition after 'Func ='. A variable holding a function needs its signature, like Func(Num) -> Num",
+                ));
             }
-        } else {
-            eprintln!("Expected '=' during assignment");
-            return None;
+            let func = self.parse_function(start)?;
+            // The ';' after the body of a function is optional
+            if self
+                .iter
+                .peek()
+                .is_some_and(|token| token.variant == TokenTypes::SEMI)
+            {
+                self.iter.next();
+            }
+            return Ok(stmt(LetKind::Func(func), self.since(start)));
         }
 
-        if i_type == Types::Function {
-            if let Some(val) = self.parse_function(&name) {
-                value = Value::Func(val);
-                let ident = IdentifierNode::new(&name, &i_type, value);
-                if let Some(table) = self.symbols.current_mut() {
-                    table.insert(name, ident.clone());
-                }
-                return Some(DecAssignNode { ident, i_type });
-            } else {
-                println!("Problem parsing function");
+        let i_type = self.get_type(false, "TYPE during declaration", start)?;
+
+        if let Some(semi) = self.iter.peek() {
+            if semi.variant == TokenTypes::SEMI {
+                self.iter.next();
+                return Ok(stmt(LetKind::Declare(i_type), self.since(start)));
             }
-        } else if i_type == Types::Bool {
-            let Some(val) = self.parse_bool() else {
-                return None;
-            };
-            let value: Value = {
-                match val {
-                    Bool::Lit(lit) => Value::Lit(Literal::Bool(lit)),
-                    Bool::Expr(expr) => Value::Expr(Expression::Bool(expr)),
-                    Bool::Ident(ident) => Value::Ident(ident),
-                    Bool::Call(call) => Value::Call(call),
+        }
+
+        let eqtoken = self.next_token("'=' during assignment", start)?;
+        if eqtoken.variant_name() != "EQ" {
+            return Err(syntax(
+                &eqtoken.span,
+                format!("Expected EQ, got {}", eqtoken.variant_name()),
+            ));
+        }
+
+        let value = self.parse_expression(0)?;
+        self.expect_semi(start, "declaration")?;
+        Ok(stmt(LetKind::Value(Some(i_type), value), self.since(start)))
+    }
+
+    /// Parses the `struct` or `enum` a type called `name` is declared as.
+    fn parse_type_definition(
+        &mut self,
+        name: &String,
+        start: &Span,
+    ) -> Result<TypeDef, Diagnostic> {
+        match self.iter.peek().map(|token| &token.variant) {
+            Some(TokenTypes::STRUCT) => self.parse_struct(name, start),
+            Some(TokenTypes::ENUM) => self.parse_enum(name, start),
+            _ => Err(syntax(start, "Expected struct or enum after 'Type ='")),
+        }
+    }
+
+    /// Parses `struct { field: Type, ... };`.
+    fn parse_struct(&mut self, name: &String, start: &Span) -> Result<TypeDef, Diagnostic> {
+        self.expect("STRUCT", start)?;
+        self.expect("LCURLY", start)?;
+        let fields = self.parse_field_list(&format!("struct {name}"), start)?;
+        self.expect("SEMI", start)?;
+        Ok(TypeDef::Struct(fields))
+    }
+
+    /// Parses `enum { Variant { field: Type, ... }, Variant, ... };`.
+    fn parse_enum(&mut self, name: &String, start: &Span) -> Result<TypeDef, Diagnostic> {
+        self.expect("ENUM", start)?;
+        self.expect("LCURLY", start)?;
+        let closing = format!("'}}' to close enum {name}");
+        let mut variants: Vec<VariantDecl> = Vec::new();
+        loop {
+            let next = self.next_token(&closing, start)?;
+            let variant = match &next.variant {
+                TokenTypes::RCURLY => break,
+                TokenTypes::IDENT { name: variant, .. } => variant.clone(),
+                _ => {
+                    return Err(syntax(
+                        &next.span,
+                        format!(
+                            "Expected a variant name in enum {name}, found {}",
+                            next.variant_name()
+                        ),
+                    ));
                 }
             };
-            let ident = IdentifierNode::new(&name, &i_type, value);
-            if let Some(table) = self.symbols.current_mut() {
-                table.insert(name, ident.clone());
+            let mut fields = Vec::new();
+            if let Some(Token {
+                variant: TokenTypes::LCURLY,
+                ..
+            }) = self.iter.peek()
+            {
+                self.iter.next();
+                fields = self.parse_field_list(&format!("{name}.{variant}"), start)?;
             }
-            return Some(DecAssignNode { ident, i_type });
-        } else if i_type == Types::Number {
-            let val = self.parse_number()?;
-            let value: Value = {
-                match val {
-                    Number::Lit(somelit) => Value::Lit(Literal::Num(somelit)),
-                    Number::Ident(someident) => Value::Ident(someident),
-                    Number::Exp(someexpr) => Value::Expr(Expression::Num(*someexpr)),
-                    Number::Call(somecall) => Value::Call(somecall),
+            variants.push(VariantDecl {
+                name: variant,
+                fields,
+                span: self.since(&next.span),
+            });
+            let next = self.next_token(&closing, start)?;
+            match next.variant {
+                TokenTypes::COMMA => continue,
+                TokenTypes::RCURLY => break,
+                _ => {
+                    return Err(syntax(
+                        &next.span,
+                        format!(
+                            "Expected ',' or '}}' in enum {name}, found {}",
+                            next.variant_name()
+                        ),
+                    ));
                 }
-            };
-            let ident = IdentifierNode::new(&name, &i_type, value);
-            if let Some(table) = self.symbols.current_mut() {
-                table.insert(name, ident.clone());
             }
-            return Some(DecAssignNode { ident, i_type });
         }
+        self.expect("SEMI", start)?;
+        Ok(TypeDef::Enum(variants))
+    }
 
-        if let Some(valtoken) = self.iter.next() {
-            match &valtoken.variant {
-                TokenTypes::NUMBER { val } => {
-                    match i_type {
-                        Types::Number => {}
-                        _ => {
-                            eprintln!(
-                                "Line {}: Expected {:?}, but got expression of type Number",
-                                valtoken.line_num, i_type
-                            );
-                            return None;
-                        }
-                    }
-                    let val = val.clone();
-                    let num = NumLiteral { val };
-                    let lit = Literal::Num(num);
-                    value = Value::Lit(lit);
-                }
-                TokenTypes::IDENT { name, isptr, isref } => {
-                    if let Some(ident) = self.symbols.search_down(&name) {
-                        if ident.i_type == Types::Function {
-                            let Some(lbrac) = self.iter.next() else {
-                                return None;
-                            };
-                            if lbrac.variant != TokenTypes::LBRACKET {
-                                eprintln!("Line {}: Expected '('", valtoken.line_num);
-                                return None;
-                            }
-                            let Some(mybox) = ident.value else {
-                                panic!();
-                            };
-                            let Value::Func(myfunc) = *mybox else {
-                                panic!();
-                            };
-                            if myfunc.ret != i_type {
-                                eprintln!(
-                                    "Line {}: Mismatched return type for call assignement",
-                                    valtoken.line_num
-                                );
-                                return None;
-                            }
-                            let Some(val) = self.parse_call(myfunc) else {
-                                return None;
-                            };
-                            value = Value::Call(val.clone());
-                        } else {
-                            if ident.i_type == i_type {
-                                value = Value::Ident(ident.clone());
-                            } else if *isptr {
-                                //TODO: it should be isderef, not isptr, as a * before a variable
-                                //name means you are extracting the value from the pointer, and
-                                //isref should be isptr, as putting a & in front turns it into a
-                                //pointer
-                                let Types::Pointer(ptrtype) = ident.i_type else {
-                                    return None;
-                                };
-                                if *ptrtype == i_type {
-                                    value = *(ident.value?).clone();
-                                }
-                            } else if *isref {
-                                let Types::Pointer(ref ptrtype) = i_type else {
-                                    return None;
-                                };
-                                if **ptrtype == ident.i_type {
-                                    value = Value::Pointer(Box::new(Value::Ident(ident)));
-                                }
-                            }
-                        }
-                    } else {
-                        eprintln!(
-                            "Line {}: No identifier {} in the current scope.",
-                            valtoken.line_num,
-                            name.to_string()
-                        );
-                    }
-                }
-                TokenTypes::BOOL { val } => {
-                    match i_type {
-                        Types::Bool => {}
-                        _ => {
-                            eprintln!(
-                                "Line {}: Expected {:?}, but got expression of type Bool",
-                                valtoken.line_num, i_type
-                            );
-                            return None;
-                        }
-                    }
-                    let boolval = {
-                        if *val {
-                            BoolLiteral::True
-                        } else {
-                            BoolLiteral::False
-                        }
-                    };
-                    value = Value::Lit(Literal::Bool(boolval));
-                }
-                TokenTypes::TEXT { text } => {
-                    match i_type {
-                        Types::String => {}
-                        _ => {
-                            eprintln!(
-                                "Line {}: Expected {:?}, but got expression of type String",
-                                valtoken.line_num, i_type
-                            );
-                            return None;
-                        }
-                    }
-                    value = Value::Lit(Literal::Text(TextLit {
-                        value: text.clone(),
-                    }));
+    /// Parses `field: Type, ... }` after the '{' of a struct or enum variant called `owner`.
+    fn parse_field_list(
+        &mut self,
+        owner: &String,
+        start: &Span,
+    ) -> Result<Vec<FieldDecl>, Diagnostic> {
+        let closing = format!("'}}' to close {owner}");
+        let mut fields: Vec<FieldDecl> = Vec::new();
+        loop {
+            let next = self.next_token(&closing, start)?;
+            let field = match &next.variant {
+                TokenTypes::RCURLY => break,
+                TokenTypes::IDENT { name: field, .. } => field.clone(),
+                _ => {
+                    return Err(syntax(
+                        &next.span,
+                        format!(
+                            "Expected a field name in {owner}, found {}",
+                            next.variant_name()
+                        ),
+                    ));
                 }
+            };
+            self.expect("COLON", &next.span)?;
+            let i_type = self.get_type(false, &format!("the TYPE of field {field}"), &next.span)?;
+            fields.push(FieldDecl {
+                name: field,
+                i_type,
+                span: self.since(&next.span),
+            });
+            let next = self.next_token(&closing, start)?;
+            match next.variant {
+                TokenTypes::COMMA => continue,
+                TokenTypes::RCURLY => break,
                 _ => {
-                    eprintln!(
-                        "Line {}: Expected a value after EQ, but got {:?}",
-                        valtoken.line_num, valtoken
-                    );
-                    return None;
+                    return Err(syntax(
+                        &next.span,
+                        format!(
+                            "Expected ',' or '}}' in {owner}, found {}",
+                            next.variant_name()
+                        ),
+                    ));
                 }
             }
-        } else {
-            eprintln!("Got EOF after EQ");
-            return None;
-        }
-        if !value.is_nothing() {
-            let ident = IdentifierNode::new(&name, &i_type, value);
-            if let Some(table) = self.symbols.current_mut() {
-                table.insert(name, ident.clone());
-            }
-            return Some(DecAssignNode {
-                ident,
-                i_type: i_type.clone(),
-            });
-        } else {
-            eprintln!("Problem parsing delcaration");
-            return None;
         }
+        Ok(fields)
     }
 
-    fn parse_number(&mut self) -> Option<Number> {
-        let next = self.iter.next()?;
-        let left: Number = {
-            match &next.variant {
-                TokenTypes::NUMBER { val } => Number::Lit(NumLiteral { val: *val }),
-                TokenTypes::IDENT {
-                    name,
-                    isptr: _,
-                    isref: _,
-                } => {
-                    let Some(numdent) = self.symbols.search_down(&name) else {
-                        eprintln!("Line {}: No identifier {} found", next.line_num, name);
-                        return None;
-                    };
-
-                    match numdent.i_type {
-                        Types::Number => Number::Ident(numdent),
-                        Types::Function => match *(numdent.value?) {
-                            Value::Func(somefunc) => {
-                                if somefunc.ret != Types::Number {
-                                    eprintln!("Mismatched types");
-                                    return None;
-                                }
-                                let callnode = self.parse_call(somefunc)?;
-                                Number::Call(callnode)
-                            }
-                            _ => {
-                                panic!();
-                            }
-                        },
-                        _ => {
-                            eprintln!("Mismatched types");
-                            return None;
-                        }
-                    }
-                }
+    /// Parses `{ field: value, ... }` after the name of a struct or variant, called `name` in
+    /// errors.
+    fn parse_literal_fields(
+        &mut self,
+        name: &String,
+        start: &Span,
+    ) -> Result<Vec<FieldInit>, Diagnostic> {
+        self.iter.next();
+        let closing = format!("'}}' to close the {name} literal");
+        let mut fields = Vec::new();
+        loop {
+            let next = self.next_token(&closing, start)?;
+            let field = match &next.variant {
+                TokenTypes::RCURLY => break,
+                TokenTypes::IDENT { name: field, .. } => field.clone(),
                 _ => {
-                    return None;
+                    return Err(syntax(
+                        &next.span,
+                        format!(
+                            "Expected a field name in {name} literal, found {}",
+                            next.variant_name()
+                        ),
+                    ));
                 }
-            }
-        };
-
-        let next = self.iter.peek()?;
-        let op = {
+            };
+            self.expect("COLON", &next.span)?;
+            let value = self.parse_inner()?;
+            fields.push(FieldInit {
+                name: field,
+                value,
+                span: self.since(&next.span),
+            });
+            let next = self.next_token(&closing, start)?;
             match next.variant {
-                TokenTypes::SEMI => {
-                    return Some(left);
-                }
-                TokenTypes::DOT => {
-                    return Some(left);
-                }
-                TokenTypes::MOD => Operators::Mod,
-                TokenTypes::PLUS => Operators::Plus,
-                TokenTypes::MINUS => Operators::Minus,
-                TokenTypes::LCURLY => {
-                    return Some(left);
-                }
+                TokenTypes::COMMA => continue,
+                TokenTypes::RCURLY => break,
                 _ => {
-                    eprintln!("Line {}: Invalid operator.", next.line_num);
-                    return None;
+                    return Err(syntax(
+                        &next.span,
+                        format!(
+                            "Expected ',' or '}}' in {name} literal, found {}",
+                            next.variant_name()
+                        ),
+                    ));
                 }
             }
-        };
-        self.iter.next();
+        }
+        Ok(fields)
+    }
 
-        let next = self.iter.next()?;
-        let right = {
-            match &next.variant {
-                TokenTypes::IDENT {
-                    name,
-                    isptr: _,
-                    isref: _,
-                } => {
-                    let Some(numdent) = self.symbols.search_down(&name) else {
-                        eprintln!(
-                            "Line {}: No identifier {} found in current scope",
-                            next.line_num, name
-                        );
-                        return None;
-                    };
-                    match numdent.i_type {
-                        Types::Number => Number::Ident(numdent),
-                        _ => {
-                            eprintln!("Line {}: Mismatched types", next.line_num);
-                            return None;
-                        }
-                    }
-                }
-                TokenTypes::NUMBER { val } => Number::Lit(NumLiteral { val: *val }),
-                _ => {
-                    return None;
-                }
+    /// Pratt parser for expressions of every type. Stops at the first token that is not a
+    /// binary operator, leaving it in the stream.
+    fn parse_expression(&mut self, min_bp: u8) -> Result<Expr, Diagnostic> {
+        let mut left = self.parse_atom()?;
+        loop {
+            let Some(next) = self.iter.peek() else {
+                break;
+            };
+            let Some(op) = BinaryOp::from_token(&next.variant) else {
+                break;
+            };
+            let (left_bp, right_bp) = op.binding_power();
+            if left_bp < min_bp {
+                break;
             }
-        };
-        let num_expression = NumExpression {
-            left: Box::new(left),
-            operator: op,
-            right: Box::new(right),
-        };
-        Some(Number::Exp(Box::new(num_expression)))
+            self.iter.next();
+            let right = self.parse_expression(right_bp)?;
+            let span = left.span.to(&right.span);
+            left = Expr {
+                kind: ExprKind::Binary(op, Box::new(left), Box::new(right)),
+                span,
+            };
+        }
+        Ok(left)
     }
 
-    fn parse_bool(&mut self) -> Option<Bool> {
+    /// Parses a literal, identifier, call, unary operation or bracketed expression.
+    fn parse_atom(&mut self) -> Result<Expr, Diagnostic> {
+        let before = self.iter.clone();
         let Some(next) = self.iter.next() else {
-            eprintln!("Could not parse bool");
-            return None;
+            return Err(Diagnostic::global(
+                ErrorCode::Syntax,
+                "Expected a value, found EOF",
+            ));
         };
+        let start = &next.span;
+        let expr = |kind: ExprKind, span: Span| Ok(Expr { kind, span });
         match &next.variant {
-            TokenTypes::BOOL { val } => {
-                let inner = {
-                    if *val {
-                        BoolLiteral::True
-                    } else {
-                        BoolLiteral::False
-                    }
-                };
-                Some(Bool::Lit(inner))
+            TokenTypes::NUMBER { val } => expr(ExprKind::Num(*val), self.since(start)),
+            TokenTypes::BOOL { val } => expr(ExprKind::Bool(*val), self.since(start)),
+            TokenTypes::TEXT { text } => expr(ExprKind::Text(text.clone()), self.since(start)),
+            TokenTypes::MINUS => {
+                let inner = self.parse_expression(UNARY_BP)?;
+                expr(ExprKind::Neg(Box::new(inner)), self.since(start))
             }
-            TokenTypes::IDENT {
-                name,
-                isptr: _,
-                isref: _,
-            } => {
-                let Some(node) = self.symbols.search_down(&name) else {
-                    eprintln!(
-                        "Line {}: No identifier {} found in current scope",
-                        next.line_num, name
-                    );
-                    return None;
+            TokenTypes::NOT => {
+                let inner = self.parse_expression(UNARY_BP)?;
+                expr(ExprKind::Not(Box::new(inner)), self.since(start))
+            }
+            TokenTypes::LBRACKET => {
+                // `() -> ...` and `(name: Type, ...) -> ...` start a lambda instead
+                let mut lookahead = self.iter.clone();
+                let lambda = match lookahead.next().map(|token| &token.variant) {
+                    Some(TokenTypes::RBRACKET) => true,
+                    Some(TokenTypes::IDENT { .. }) => lookahead
+                        .next()
+                        .is_some_and(|token| token.variant == TokenTypes::COLON),
+                    _ => false,
                 };
-                if node.i_type == Types::Bool {
-                    Some(Bool::Ident(node.clone()))
-                } else if node.i_type == Types::Function {
-                    let lbrac = self.iter.next()?;
-                    if lbrac.variant != TokenTypes::LBRACKET {
-                        eprintln!(
-                            "Line {}: Expected '(', found {} ",
-                            lbrac.line_num,
-                            lbrac.variant_name()
+                if lambda {
+                    self.iter = before;
+                    let func = self.parse_function(start)?;
+                    return expr(ExprKind::Lambda(func), self.since(start));
+                }
+                let inner = self.parse_inner()?;
+                self.expect("RBRACKET", start)?;
+                Ok(Expr {
+                    span: self.since(start),
+                    ..inner
+                })
+            }
+            TokenTypes::LSQUARE => self.parse_vec_literal(start),
+            TokenTypes::IDENT { name, isptr, isref } => {
+                if *isref {
+                    return expr(ExprKind::Ref(name.clone()), self.since(start));
+                }
+                if *isptr {
+                    if self.next_is_field() {
+                        return Err(Diagnostic::error(
+                            ErrorCode::Unsupported,
+                            start,
+                            "Dereferencing a field is coming soon, store it in a variable first",
+                        ));
+                    }
+                    return expr(ExprKind::Deref(name.clone()), self.since(start));
+                }
+                match self.iter.peek().map(|token| &token.variant) {
+                    Some(TokenTypes::LBRACKET) => {
+                        self.iter.next();
+                        let call = self.parse_call(name, start)?;
+                        return expr(ExprKind::Call(call), self.since(start));
+                    }
+                    Some(TokenTypes::LSQUARE) => {
+                        let index = self.parse_subscript(start)?;
+                        return expr(
+                            ExprKind::Index(name.clone(), Box::new(index)),
+                            self.since(start),
                         );
                     }
-                    match *node.value? {
-                        Value::Func(func) => {
-                            let callnode = self.parse_call(func)?;
-                            Some(Bool::Call(callnode))
-                        }
-                        _ => panic!(),
+                    _ => {}
+                }
+                if self.next_is_literal() {
+                    let fields = self.parse_literal_fields(name, start)?;
+                    return expr(
+                        ExprKind::Literal(name.clone(), None, fields),
+                        self.since(start),
+                    );
+                }
+                if !self.next_is_field() {
+                    return expr(ExprKind::Ident(name.clone()), self.since(start));
+                }
+                let path = self.parse_field_path(start)?;
+                if let [variant] = path.as_slice() {
+                    if self.next_is_literal() {
+                        let fields =
+                            self.parse_literal_fields(&format!("{name}.{variant}"), start)?;
+                        return expr(
+                            ExprKind::Literal(name.clone(), Some(variant.clone()), fields),
+                            self.since(start),
+                        );
                     }
-                } else {
-                    Some(Bool::Expr(self.parse_bool_expression(node.clone())?))
                 }
+                expr(ExprKind::Field(name.clone(), path), self.since(start))
             }
-            _ => todo!(),
+            _ => Err(syntax(
+                &next.span,
+                format!("Expected a value, found {}", next.variant_name()),
+            )),
         }
     }
 
-    fn parse_for(&mut self, functype: Types) -> Option<ForNode> {
-        let ident = self.iter.next()?;
-
-        let ident: IdentifierNode = match &ident.variant {
-            TokenTypes::IDENT {
-                name,
-                isptr: _,
-                isref: _,
-            } => IdentifierNode {
-                name: name.to_string(),
-                i_type: Types::Number,
-                value: None,
-            },
-            _ => {
-                return None;
+    /// Parses the elements of the Vec `[a, b, c]` or the array `[value; len]` after the '['.
+    fn parse_vec_literal(&mut self, start: &Span) -> Result<Expr, Diagnostic> {
+        let mut elements = Vec::new();
+        if let Some(rsquare) = self.iter.peek() {
+            if rsquare.variant == TokenTypes::RSQUARE {
+                self.iter.next();
+                let kind = ExprKind::Vec(elements);
+                return Ok(Expr {
+                    kind,
+                    span: self.since(start),
+                });
+            }
+        }
+        loop {
+            let value = self.parse_inner()?;
+            if elements.is_empty() {
+                if let Some(semi) = self.iter.peek() {
+                    if semi.variant == TokenTypes::SEMI {
+                        return self.parse_array_literal(value, start);
+                    }
+                }
+            }
+            elements.push(value);
+            let next = self.next_token("']' to close the Vec literal", start)?;
+            match next.variant {
+                TokenTypes::COMMA => continue,
+                TokenTypes::RSQUARE => break,
+                _ => {
+                    return Err(syntax(
+                        &next.span,
+                        format!(
+                            "Expected ',' or ']' in Vec literal, found {}",
+                            next.variant_name()
+                        ),
+                    ));
+                }
             }
-        };
-
-        let inkw = self.iter.next()?;
-        if inkw.variant != TokenTypes::IN {
-            return None;
         }
+        let kind = ExprKind::Vec(elements);
+        Ok(Expr {
+            kind,
+            span: self.since(start),
+        })
+    }
 
-        let start = self.parse_number()?;
+    /// Parses `; len]` after the value of an array literal. The length has to be a Num literal,
+    /// as it is part of the type.
+    fn parse_array_literal(&mut self, value: Expr, start: &Span) -> Result<Expr, Diagnostic> {
+        self.iter.next();
+        let len = self.parse_array_len(start)?;
+        self.expect("RSQUARE", start)?;
+        let kind = ExprKind::Array(Box::new(value), len);
+        Ok(Expr {
+            kind,
+            span: self.since(start),
+        })
+    }
 
-        let (dot1, dot2) = (self.iter.next()?, self.iter.next()?);
-        if !(dot1.variant == dot2.variant && dot2.variant == TokenTypes::DOT) {
-            return None;
+    fn parse_array_len(&mut self, start: &Span) -> Result<usize, Diagnostic> {
+        let len = self.next_token("the length of the array", start)?;
+        match len.variant {
+            TokenTypes::NUMBER { val } if val > 0 => Ok(val as usize),
+            _ => Err(syntax(
+                &len.span,
+                "The length of an array must be a Num literal of at least 1",
+            )),
         }
+    }
 
-        let end = self.parse_number()?;
+    /// Parses `[index]`.
+    fn parse_subscript(&mut self, start: &Span) -> Result<Expr, Diagnostic> {
+        self.iter.next();
+        let index = self.parse_inner()?;
+        self.expect("RSQUARE", start)?;
+        Ok(index)
+    }
 
-        let lcurly = self.iter.next()?;
-        if lcurly.variant != TokenTypes::LCURLY {
-            return None;
+    /// Parses `'label: while ...` or `'label: for ...` after the label token.
+    fn parse_labelled_loop(&mut self, name: &String, start: &Span) -> Result<Stmt, Diagnostic> {
+        self.expect("COLON", start)?;
+        let kw = self.next_token(&format!("a loop after label '{name}"), start)?;
+        let label = Some(name.clone());
+        match kw.variant {
+            TokenTypes::FOR => Ok(Stmt::For(self.parse_for(label, &kw.span)?)),
+            TokenTypes::WHILE => Ok(Stmt::While(self.parse_while(label, &kw.span)?)),
+            _ => Err(syntax(
+                &kw.span,
+                format!("Only loops can be labelled, found {}", kw.variant_name()),
+            )),
         }
+    }
 
-        let body = self.parse_until(
-            TokenTypes::RCURLY,
-            Some(vec![ident.clone()]),
-            Some(functype),
-        )?;
-
-        let range = RangeNode { start, end };
+    /// Parses the rest of a `break` or `continue`, which may name the loop it applies to.
+    fn parse_loop_control(&mut self, keyword: &str, start: &Span) -> Result<JumpStmt, Diagnostic> {
+        let mut label = None;
+        let expected = format!("SEMI after {keyword}");
+        let mut next = self.next_token(&expected, start)?;
+        if let TokenTypes::LABEL { name } = &next.variant {
+            label = Some(name.clone());
+            next = self.next_token(&expected, start)?;
+        }
+        if next.variant != TokenTypes::SEMI {
+            return Err(syntax(
+                &next.span,
+                format!("Expected {expected}, found {}", next.variant_name()),
+            ));
+        }
+        Ok(JumpStmt {
+            label,
+            span: self.since(start),
+        })
+    }
 
-        Some(ForNode {
-            dec: ident,
-            range,
+    fn parse_while(
+        &mut self,
+        label: Option<String>,
+        start: &Span,
+    ) -> Result<WhileStmt, Diagnostic> {
+        let condition = self.parse_head()?;
+        self.expect("LCURLY", start)?;
+        let body = self.parse_until(TokenTypes::RCURLY)?;
+        Ok(WhileStmt {
+            condition,
             body,
+            label,
+            span: self.since(start),
         })
     }
 
-    fn parse_function(&mut self, name: &String) -> Option<Function> {
-        let mut fnparams: Option<Vec<IdentifierNode>> = None;
-        let mut hierdiefunksie: Option<Function> = None;
-        let mut rettype = Types::Nothing;
-        if let Some(lbrac) = self.iter.next() {
-            if !Self::val_token(lbrac, "LBRACKET") {
-                return None;
-            }
-        } else {
-            return None;
-        }
+    /// Parses `for x in start..end {` or `for x in vec {`, followed by the loop body.
+    fn parse_for(&mut self, label: Option<String>, start: &Span) -> Result<ForStmt, Diagnostic> {
+        let identtkn = self.next_token("the name of the loop variable", start)?;
+        let TokenTypes::IDENT { name, .. } = &identtkn.variant else {
+            return Err(syntax(
+                &identtkn.span,
+                format!(
+                    "Expected the name of the loop variable after for, found {}",
+                    identtkn.variant_name()
+                ),
+            ));
+        };
 
-        if let Some(params) = self.parse_params() {
-            fnparams = Some(params);
-        } else {
-            eprintln!("Could not parse params");
-            return None;
-        }
+        self.expect("IN", start)?;
 
-        if let Some(arrow) = self.iter.next() {
-            if !Self::val_token(&arrow, "ARROW") {
-                return None;
-            }
+        let first = self.parse_head()?;
+        let range = self
+            .iter
+            .peek()
+            .is_some_and(|token| token.variant == TokenTypes::DOT);
+        let iter = if range {
+            self.expect("DOT", start)?;
+            self.expect("DOT", start)?;
+            let end = self.parse_head()?;
+            ForIter::Range(first, end)
         } else {
-            return None;
-        }
-
-        if let Some(rettipe) = self.iter.next() {
-            match rettipe.variant {
-                TokenTypes::NOTHINGTYPE => {}
-                TokenTypes::NUMTYPE(isptr) => {
-                    rettype = {
-                        if isptr {
-                            Types::Pointer(Box::new(Types::Number))
-                        } else {
-                            Types::Number
-                        }
-                    }
-                }
-                TokenTypes::TEXTTYPE(isptr) => {
-                    rettype = {
-                        if isptr {
-                            Types::Pointer(Box::new(Types::String))
-                        } else {
-                            Types::String
-                        }
-                    }
-                }
-                TokenTypes::BOOLTYPE(isptr) => {
-                    rettype = {
-                        if isptr {
-                            Types::Pointer(Box::new(Types::Bool))
-                        } else {
-                            Types::Bool
-                        }
-                    }
-                }
-                _ => {
-                    eprintln!("line {}: Expected RETURN TYPE of function. \nIf your function does not return, use 'Nothing'", rettipe.line_num);
-                    return None;
-                }
-            }
-        }
+            ForIter::Elements(first)
+        };
 
-        if let Some(lcurl) = self.iter.next() {
-            if !Self::val_token(&lcurl, "LCURLY") {
-                return None;
-            }
-        }
+        self.expect("LCURLY", start)?;
 
-        if let Some(block) = self.parse_until(
-            TokenTypes::RCURLY,
-            Some(fnparams.clone())?,
-            Some(rettype.clone()),
-        ) {
-            let func = Function {
-                name: name.clone(),
-                params: Some(fnparams)??,
-                ret: rettype.clone(),
-                body: block,
-            };
-            hierdiefunksie = Some(func.clone());
-        }
-        hierdiefunksie
+        let body = self.parse_until(TokenTypes::RCURLY)?;
+        Ok(ForStmt {
+            name: name.clone(),
+            iter,
+            body,
+            label,
+            span: self.since(start),
+        })
     }
 
-    fn val_token(base: &Token, target: &str) -> bool {
-        if !(base.variant_name() == target) {
-            eprintln!(
-                "Line {}: Expected {}, but found {}",
-                base.line_num,
-                target,
-                base.variant_name()
-            );
-            return false;
-        }
-        true
+    /// Parses `(params) -> Type { ... }`, the definition of a function or a lambda. Its span
+    /// starts at the '(', even when `start` is the `let` defining it.
+    fn parse_function(&mut self, start: &Span) -> Result<FuncExpr, Diagnostic> {
+        let open = self.expect("LBRACKET", start)?;
+        let params = self.parse_params(start)?;
+        self.expect("ARROW", start)?;
+        let ret = self.get_type(true, "RETURN TYPE of function", start)?;
+        self.expect("LCURLY", start)?;
+
+        let body = self.parse_until(TokenTypes::RCURLY)?;
+        Ok(FuncExpr {
+            params,
+            ret,
+            body,
+            span: self.since(&open.span),
+        })
     }
 
-    fn parse_params(&mut self) -> Option<Vec<IdentifierNode>> {
-        let mut params: Vec<IdentifierNode> = Vec::new();
-        let mut paramname = String::new();
-        let mut paramtype = Types::Nothing;
-        'mainloop: while let Some(param) = self.iter.next() {
+    fn parse_params(&mut self, start: &Span) -> Result<Vec<Param>, Diagnostic> {
+        let mut params: Vec<Param> = Vec::new();
+        loop {
+            let param = self.next_token("')' to close the parameters", start)?;
             if param.variant == TokenTypes::RBRACKET {
-                break 'mainloop;
+                break;
             }
             match &param.variant {
                 TokenTypes::IDENT {
@@ -851,351 +955,387 @@ impl<'a> ParseTree<'a> {
                     isptr: _,
                     isref: _,
                 } => {
-                    paramname = name.to_string();
-                    if self.iter.peek().is_some() {
-                        let colon = self.iter.next().unwrap();
-                        if colon.variant == TokenTypes::COLON {
-                            let mut mytype = self.get_type(false);
-                            if mytype.is_none() {
-                                return None;
-                            }
-                            let mytype = mytype.unwrap();
-                            paramtype = mytype.clone();
-                        } else {
-                            eprintln!(
-                                "Line {}: Expected COLON, found {:?}",
-                                colon.line_num,
-                                colon.variant_name()
-                            );
-                            return None;
-                        }
-                    } else {
-                        eprintln!("Unexpected EOF");
-                        return None;
+                    let colon = self.next_token("COLON", &param.span)?;
+                    if colon.variant != TokenTypes::COLON {
+                        return Err(syntax(
+                            &colon.span,
+                            format!("Expected COLON, found {:?}", colon.variant_name()),
+                        ));
                     }
+                    let i_type = self.get_type(
+                        false,
+                        &format!("the TYPE of parameter {name}"),
+                        &param.span,
+                    )?;
+                    params.push(Param {
+                        name: name.clone(),
+                        i_type,
+                        span: self.since(&param.span),
+                    });
                 }
                 TokenTypes::COMMA => {
                     continue;
                 }
                 _ => {
-                    eprintln!(
-                        "Line {}: Expected parameter, found {}",
-                        param.line_num,
-                        param.variant_name()
-                    );
-                    return None;
+                    return Err(syntax(
+                        &param.span,
+                        format!("Expected parameter, found {}", param.variant_name()),
+                    ));
                 }
             }
-            let newident = IdentifierNode {
-                name: paramname,
-                i_type: paramtype,
-                value: None,
-            };
-            params.push(newident);
-            paramname = String::new();
-            paramtype = Types::Nothing;
         }
-        Some(params)
+        Ok(params)
     }
 
-    fn get_type(&mut self, allow_nothing: bool) -> Option<Types> {
-        if let Some(typeid) = self.iter.next() {
-            match &typeid.variant {
-                TokenTypes::FUNCTYPE => Some(Types::Function),
-                TokenTypes::NUMTYPE(isptr) => {
-                    if *isptr {
-                        Some(Types::Pointer(Box::new(Types::Number)))
-                    } else {
-                        Some(Types::Number)
-                    }
-                }
-                TokenTypes::TEXTTYPE(isptr) => {
-                    if *isptr {
-                        Some(Types::Pointer(Box::new(Types::String)))
-                    } else {
-                        Some(Types::String)
-                    }
-                }
-                TokenTypes::BOOLTYPE(isptr) => {
-                    if *isptr {
-                        Some(Types::Pointer(Box::new(Types::Bool)))
-                    } else {
-                        Some(Types::Bool)
-                    }
-                }
-                TokenTypes::NOTHINGTYPE => {
-                    if allow_nothing {
-                        Some(Types::Nothing)
-                    } else {
-                        None
+    /// Parses `(Type, ...) -> Type` after `Func` in a type.
+    fn parse_func_type(&mut self, start: &Span) -> Result<TypeKind, Diagnostic> {
+        if !self
+            .iter
+            .peek()
+            .is_some_and(|token| token.variant == TokenTypes::LBRACKET)
+        {
+            return Err(syntax(
+                start,
+                "Func needs its signature here, like Func(Num, Num) -> Bool",
+            ));
+        }
+        self.iter.next();
+        let mut params = Vec::new();
+        if self
+            .iter
+            .peek()
+            .is_some_and(|token| token.variant == TokenTypes::RBRACKET)
+        {
+            self.iter.next();
+        } else {
+            loop {
+                params.push(self.get_type(false, "the TYPE of a parameter in Func(...)", start)?);
+                let next = self.next_token("')' to close Func(...)", start)?;
+                match next.variant {
+                    TokenTypes::COMMA => continue,
+                    TokenTypes::RBRACKET => break,
+                    _ => {
+                        return Err(syntax(
+                            &next.span,
+                            format!(
+                                "Expected ',' or ')' in Func(...), found {}",
+                                next.variant_name()
+                            ),
+                        ));
                     }
                 }
-                _ => None,
             }
-        } else {
-            eprintln!("Expected type annotation, got EOF");
-            return None;
         }
+        self.expect("ARROW", start)?;
+        let ret = self.get_type(true, "the RETURN TYPE of Func(...)", start)?;
+        Ok(TypeKind::Function(params, Box::new(ret)))
     }
 
-    fn parse_conditional(&mut self, functype: Types) -> Option<ConditionalNode> {
-        let mut thisbool = Bool::Lit(BoolLiteral::False);
-        if let Some(next) = self.iter.next() {
-            match &next.variant {
-                TokenTypes::IDENT {
-                    name,
-                    isptr: _,
-                    isref: _,
-                } => {
-                    if let Some(boolident) = self.symbols.search_down(&name) {
-                        if boolident.i_type == Types::Bool {
-                            thisbool = Bool::Ident(boolident.clone());
-                        } else {
-                            // case of ident after if, will check for bool
-                            // expression eg. ident1 == ident2
-                            let Some(expr) = self.parse_bool_expression(boolident.clone()) else {
-                                eprintln!(
-                                    "Line {}: Could not parse bool expression.",
-                                    next.line_num
-                                );
-                                return None;
-                            };
-                            thisbool = Bool::Expr(expr);
-                        }
-                    } else {
-                        eprintln!("Could not find identifier {name} in current scope");
-                        return None;
-                    }
-                }
-                TokenTypes::BOOL { val } => {
-                    thisbool = {
-                        if *val {
-                            Bool::Lit(BoolLiteral::True)
-                        } else {
-                            Bool::Lit(BoolLiteral::False)
-                        }
-                    };
+    /// Parses a type, `Nothing` included when `allow_nothing` is set. `expected` says what the
+    /// type is for, and `start` is the span of the token the construct it belongs to starts
+    /// with.
+    fn get_type(
+        &mut self,
+        allow_nothing: bool,
+        expected: &str,
+        start: &Span,
+    ) -> Result<TypeExpr, Diagnostic> {
+        let typeid = self.next_token(expected, start)?;
+        let start = &typeid.span;
+        // The lexer marks `*Num` and the like as pointers on the type token itself
+        let pointer = |kind: TypeKind, isptr: bool| {
+            if isptr {
+                let span = start.clone();
+                TypeKind::Pointer(Box::new(TypeExpr { kind, span }))
+            } else {
+                kind
+            }
+        };
+        let kind = match &typeid.variant {
+            TokenTypes::FUNCTYPE => self.parse_func_type(start)?,
+            TokenTypes::NUMTYPE(isptr) => pointer(TypeKind::Number, *isptr),
+            TokenTypes::TEXTTYPE(isptr) => pointer(TypeKind::String, *isptr),
+            TokenTypes::BOOLTYPE(isptr) => pointer(TypeKind::Bool, *isptr),
+            TokenTypes::VECTYPE(isptr) => {
+                self.expect("LSQUARE", start)?;
+                let element = self.get_type(false, "the element TYPE of the Vec", start)?;
+                self.expect("RSQUARE", start)?;
+                pointer(TypeKind::Vec(Box::new(element)), *isptr)
+            }
+            TokenTypes::LSQUARE => {
+                let element = self.get_type(false, "the element TYPE of the array", start)?;
+                self.expect("SEMI", start)?;
+                let len = self.parse_array_len(start)?;
+                self.expect("RSQUARE", start)?;
+                TypeKind::Array(Box::new(element), len)
+            }
+            // Types the lexer could not mark as pointers, like arrays
+            TokenTypes::STAR => {
+                let mutable = self
+                    .iter
+                    .peek()
+                    .is_some_and(|token| token.variant == TokenTypes::MUT);
+                if mutable {
+                    self.iter.next();
                 }
-                _ => {
-                    eprintln!(
-                        "Line {}: Expected bool literal or bool identifier.",
-                        next.line_num
-                    );
-                    return None;
+                let inner = Box::new(self.get_type(false, "the TYPE pointed to", start)?);
+                if mutable {
+                    TypeKind::MutPointer(inner)
+                } else {
+                    TypeKind::Pointer(inner)
                 }
             }
-            let Some(lcurly) = self.iter.next() else {
-                eprintln!("Unexpected EOF");
-                return None;
-            };
-            if lcurly.variant == TokenTypes::LCURLY {
-                let Some(body) = self.parse_until(TokenTypes::RCURLY, None, Some(functype.clone()))
-                else {
-                    eprintln!(
-                        "Line {}: Could not parse conditional body.",
-                        lcurly.line_num
-                    );
-                    return None;
-                };
-                let s_else = self.iter.peek()?;
-                if s_else.variant != TokenTypes::ELSE {
-                    let condnode = ConditionalNode {
-                        condition: thisbool,
-                        body,
-                        i_else: None,
-                    };
-                    return Some(condnode);
-                }
-                let s_else = self.iter.next()?;
-                let Some(lcurly) = self.iter.next() else {
-                    eprintln!("Line {}: Expected '{{'", s_else.line_num);
-                    return None;
-                };
-                if lcurly.variant != TokenTypes::LCURLY {
-                    eprintln!("Line {}: Expected '{{'", lcurly.line_num);
-                    return None;
+            TokenTypes::IDENT { name, isptr, .. } => pointer(TypeKind::Named(name.clone()), *isptr),
+            TokenTypes::NOTHINGTYPE if allow_nothing => TypeKind::Nothing,
+            TokenTypes::MUT => match self
+                .get_type(false, "a pointer TYPE after mut", start)?
+                .kind
+            {
+                TypeKind::Pointer(inner) => TypeKind::MutPointer(inner),
+                _ => {
+                    return Err(Diagnostic::error(
+                        ErrorCode::Immutable,
+                        start,
+                        "Only pointer types can be marked mut",
+                    ));
                 }
-                let Some(elsebody) = self.parse_until(TokenTypes::RCURLY, None, Some(functype))
-                else {
-                    eprintln!("Line {}: Could not parse else body", lcurly.line_num);
-                    return None;
-                };
-                let condnode = ConditionalNode {
-                    condition: thisbool,
-                    body,
-                    i_else: Some(elsebody),
+            },
+            _ => {
+                let hint = if allow_nothing {
+                    ". If it does not return, use 'Nothing'"
+                } else {
+                    ""
                 };
-                return Some(condnode);
+                return Err(syntax(
+                    start,
+                    format!("Expected {expected}, found {}{hint}", typeid.variant_name()),
+                ));
             }
-        }
-        eprintln!("Unexpected EOF");
-        None
+        };
+        Ok(TypeExpr {
+            kind,
+            span: self.since(start),
+        })
     }
 
-    //TODO: why is it getting here when declaring a boolean in the body of an if statement?
-    fn parse_bool_expression(&mut self, left: IdentifierNode) -> Option<BoolExpr> {
-        let mut op = BoolOps::EQ;
-        let Some(next) = self.iter.next() else {
-            return None;
-        };
-        let mut right = Token::new(TokenTypes::NULL, 0);
-        match next.variant {
-            TokenTypes::BOOLEQ => {
-                op = BoolOps::EQ;
-            }
-            TokenTypes::GREATER => {
-                op = BoolOps::Greater;
+    fn parse_conditional(&mut self, start: &Span) -> Result<IfStmt, Diagnostic> {
+        let condition = self.parse_head()?;
+        let lcurly = self.next_token("'{'", start)?;
+        if lcurly.variant != TokenTypes::LCURLY {
+            return Err(syntax(&lcurly.span, "Expected '{'"));
+        }
+        let body = self.parse_until(TokenTypes::RCURLY)?;
+        let mut elifs = Vec::new();
+        let mut i_else = None;
+        while let Some(s_else) = self.iter.peek() {
+            if s_else.variant != TokenTypes::ELSE {
+                break;
             }
-            TokenTypes::LESSER => {
-                op = BoolOps::Lesser;
+            let else_token: &'a Token = *s_else;
+            self.iter.next();
+            let next = self.next_token("'{' or 'if' after else", &else_token.span)?;
+            if next.variant == TokenTypes::IF {
+                let condition = self.parse_head()?;
+                self.expect("LCURLY", &next.span)?;
+                let elifbody = self.parse_until(TokenTypes::RCURLY)?;
+                elifs.push((condition, elifbody));
+                continue;
             }
-            _ => {
-                eprintln!("Unexpected boolean operator");
-                return None;
+            if next.variant != TokenTypes::LCURLY {
+                return Err(syntax(&next.span, "Expected '{'"));
             }
+            let elsebody = self.parse_until(TokenTypes::RCURLY)?;
+            i_else = Some(elsebody);
+            break;
         }
+        Ok(IfStmt {
+            condition,
+            body,
+            elifs,
+            i_else,
+            span: self.since(start),
+        })
+    }
 
-        let Some(rightc) = self.iter.next() else {
-            eprintln!(
-                "Line {}: Expected an expression to the right of '=='",
-                next.line_num
-            );
-            return None;
-        };
-        right = rightc.clone();
-
-        match &right.variant {
-            TokenTypes::IDENT {
-                name,
-                isptr: _,
-                isref: _,
-            } => {
-                let Some(rightnode) = self.symbols.search_down(&name) else {
-                    eprintln!(
-                        "Line {}: Could not find identifier {} in the current scope",
-                        right.line_num, name,
-                    );
-                    return None;
-                };
-                if rightnode.i_type != left.i_type {
-                    eprintln!(
-                        "Line {}: Cannot compare values of different types.",
-                        right.line_num
-                    );
-                    return None;
+    /// Parses `match value { Variant { field, field: name } => { ... } ... }` after `match`.
+    fn parse_match(&mut self, start: &Span) -> Result<MatchStmt, Diagnostic> {
+        let value = self.parse_head()?;
+        self.expect("LCURLY", start)?;
+        let mut arms: Vec<ArmStmt> = Vec::new();
+        loop {
+            let next = self.next_token("'}' to close the match", start)?;
+            let armstart = &next.span;
+            let variant = match &next.variant {
+                TokenTypes::RCURLY => break,
+                TokenTypes::IDENT { name: variant, .. } => variant.clone(),
+                _ => {
+                    return Err(syntax(
+                        armstart,
+                        format!("Expected a variant or '_', found {}", next.variant_name()),
+                    ));
                 }
-                let expr = BoolExpr {
-                    left: Box::new(Value::Ident(left)),
-                    operator: op,
-                    right: Box::new(Value::Ident(rightnode.clone())),
-                };
-                return Some(expr);
-            }
-            _ => {
-                eprintln!(
-                    "Line {}: Only identifers can be used in boolean expressions",
-                    right.line_num
-                );
-                return None;
+            };
+            let mut bindings = Vec::new();
+            let variant = if variant == "_" {
+                None
+            } else {
+                if let Some(Token {
+                    variant: TokenTypes::LCURLY,
+                    ..
+                }) = self.iter.peek()
+                {
+                    self.iter.next();
+                    bindings = self.parse_bindings(&variant, armstart)?;
+                }
+                Some(variant)
+            };
+            self.expect("FATARROW", armstart)?;
+            self.expect("LCURLY", armstart)?;
+            let body = self.parse_until(TokenTypes::RCURLY)?;
+            arms.push(ArmStmt {
+                variant,
+                bindings,
+                body,
+                span: self.since(armstart),
+            });
+            if let Some(Token {
+                variant: TokenTypes::COMMA,
+                ..
+            }) = self.iter.peek()
+            {
+                self.iter.next();
             }
         }
+        Ok(MatchStmt {
+            value,
+            arms,
+            span: self.since(start),
+        })
     }
 
-    pub fn parse_call(&mut self, func: Function) -> Option<CallNode> {
-        let mut args = Vec::new();
-        let mut nocomma = false;
-        while let Some(param) = self.iter.next() {
-            match &param.variant {
-                TokenTypes::RBRACKET => {
-                    break;
-                }
-                TokenTypes::IDENT {
-                    name,
-                    isptr: _,
-                    isref: _,
-                } => {
-                    args.push(self.parse_arg_ident(name, param.line_num)?);
-                }
-                TokenTypes::NUMBER { val } => {
-                    args.push(Value::Lit(Literal::Num(NumLiteral { val: val.clone() })));
-                }
-                TokenTypes::TEXT { text: text1 } => {
-                    args.push(Value::Lit(Literal::Text(TextLit {
-                        value: text1.clone(),
-                    })));
-                }
-                TokenTypes::BOOL { val } => {
-                    let inner = {
-                        if *val {
-                            BoolLiteral::True
-                        } else {
-                            BoolLiteral::False
-                        }
-                    };
-                    args.push(Value::Lit(Literal::Bool(inner)));
+    /// Parses `field, field: name, ... }` after the '{' of a match arm for the variant
+    /// `variant`.
+    fn parse_bindings(
+        &mut self,
+        variant: &String,
+        start: &Span,
+    ) -> Result<Vec<Binding>, Diagnostic> {
+        let closing = format!("'}}' to close the fields of {variant}");
+        let mut bindings: Vec<Binding> = Vec::new();
+        loop {
+            let next = self.next_token(&closing, start)?;
+            let field = match &next.variant {
+                TokenTypes::RCURLY => break,
+                TokenTypes::IDENT { name: field, .. } => field.clone(),
+                _ => {
+                    return Err(syntax(
+                        &next.span,
+                        format!(
+                            "Expected a field of {variant}, found {}",
+                            next.variant_name()
+                        ),
+                    ));
                 }
-                _ => {}
-            }
-            let comma = self.iter.peek()?;
-            if comma.variant == TokenTypes::COMMA {
-                self.iter.next();
-            } else if comma.variant == TokenTypes::RBRACKET {
+            };
+            let mut name = field.clone();
+            if let Some(Token {
+                variant: TokenTypes::COLON,
+                ..
+            }) = self.iter.peek()
+            {
                 self.iter.next();
-                break;
+                let Some(Token {
+                    variant: TokenTypes::IDENT { name: rename, .. },
+                    ..
+                }) = self.iter.next()
+                else {
+                    return Err(syntax(
+                        &next.span,
+                        format!("Expected a name to bind {field} to after ':'"),
+                    ));
+                };
+                name = rename.clone();
+            }
+            bindings.push(Binding {
+                field,
+                name,
+                span: self.since(&next.span),
+            });
+            let next = self.next_token(&closing, start)?;
+            match next.variant {
+                TokenTypes::COMMA => continue,
+                TokenTypes::RCURLY => break,
+                _ => {
+                    return Err(syntax(
+                        &next.span,
+                        format!(
+                            "Expected ',' or '}}' after a field of {variant}, found {}",
+                            next.variant_name()
+                        ),
+                    ));
+                }
             }
         }
+        Ok(bindings)
+    }
 
-        for val in &args {
-            match val {
-                Value::Nothing => panic!("not valid"),
-                _ => {}
+    /// Parses the arguments of a call to `name`. The opening '(' must already be consumed.
+    pub fn parse_call(&mut self, name: &String, start: &Span) -> Result<CallExpr, Diagnostic> {
+        let mut args = Vec::new();
+        if self
+            .iter
+            .peek()
+            .is_some_and(|token| token.variant == TokenTypes::RBRACKET)
+        {
+            self.iter.next();
+        } else {
+            let closing = format!("')' to close the call to {name}");
+            loop {
+                args.push(self.parse_inner()?);
+                let next = self.next_token(&closing, start)?;
+                match next.variant {
+                    TokenTypes::COMMA => continue,
+                    TokenTypes::RBRACKET => break,
+                    _ => {
+                        return Err(syntax(
+                            &next.span,
+                            format!(
+                                "Expected ',' or ')' in the call to {name}, found {}",
+                                next.variant_name()
+                            ),
+                        ));
+                    }
+                }
             }
         }
-        // dbg!(args.clone());
+        Ok(CallExpr {
+            name: name.clone(),
+            args,
+            span: self.since(start),
+        })
+    }
+}
 
-        if func.name == String::from("showme") {
-            let params_res: Option<Vec<IdentifierNode>> = args
-                .into_iter()
-                .map(|val| IdentifierNode::from(val.clone()))
-                .collect();
-            let params = params_res?;
-            return Some(CallNode {
-                func: func.clone(),
-                params,
-            });
-        }
+/// Binding power of prefix '-' and '!', tighter than every binary operator.
+const UNARY_BP: u8 = 11;
 
-        if args.len() != func.params.len() {
-            eprintln!(
-                "Wrong number of arguments for call to function {}",
-                func.name
-            );
-            return None;
-        }
-        let mut params_res: Option<Vec<IdentifierNode>> = args
-            .into_iter()
-            .map(|val| IdentifierNode::from(val.clone()))
-            .collect();
-        let params = params_res?;
-        for (index, param) in func.params.iter().enumerate() {
-            if param.i_type != params[index].i_type {
-                return None;
-            }
+impl BinaryOp {
+    fn from_token(token: &TokenTypes) -> Option<Self> {
+        if let Some(op) = Operators::from_token(token) {
+            return Some(BinaryOp::Num(op));
         }
-        Some(CallNode {
-            func: func.clone(),
-            params,
-        })
+        BoolOps::from_token(token).map(BinaryOp::Bool)
     }
 
-    fn parse_arg_ident(&self, name: &String, line: usize) -> Option<Value> {
-        let Some(idnode) = self.symbols.search_down(name) else {
-            eprintln!("Line {line}: No identifier {name} found");
-            return None;
-        };
-        match idnode.i_type {
-            Types::Function | Types::Nothing => None,
-            _ => Some(Value::Ident(idnode.clone())),
+    /// Left and right binding powers, from loosest to tightest: `||`, `&&`, comparisons,
+    /// `+ -`, `* / %`. All operators are left associative.
+    fn binding_power(&self) -> (u8, u8) {
+        match self {
+            BinaryOp::Bool(BoolOps::Or) => (1, 2),
+            BinaryOp::Bool(BoolOps::And) => (3, 4),
+            BinaryOp::Bool(_) => (5, 6),
+            BinaryOp::Num(Operators::Plus | Operators::Minus) => (7, 8),
+            BinaryOp::Num(_) => (9, 10),
         }
     }
 }
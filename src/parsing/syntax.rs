@@ -0,0 +1,268 @@
+use crate::diagnostics::diagnostic::Span;
+use crate::parsing::nodes::{BoolOps, Operators};
+
+/// The program as it is written, before any name is resolved or any type is checked. The parser
+/// builds it, and `analysis::typeck` turns it into the typed nodes of `parsing::nodes`. Every
+/// node keeps the span of source it was parsed from, for the errors found in it later.
+#[derive(Debug, Clone)]
+pub enum Stmt {
+    Let(LetStmt),
+    Assign(AssignStmt),
+    Call(CallExpr),
+    If(IfStmt),
+    Return(ReturnStmt),
+    Inline(InlineStmt),
+    For(ForStmt),
+    While(WhileStmt),
+    Break(JumpStmt),
+    Continue(JumpStmt),
+    Match(MatchStmt),
+}
+
+impl Stmt {
+    /// Source the statement was parsed from.
+    pub fn span(&self) -> &Span {
+        match self {
+            Stmt::Let(stmt) => &stmt.span,
+            Stmt::Assign(stmt) => &stmt.span,
+            Stmt::Call(call) => &call.span,
+            Stmt::If(stmt) => &stmt.span,
+            Stmt::Return(stmt) => &stmt.span,
+            Stmt::Inline(stmt) => &stmt.span,
+            Stmt::For(stmt) => &stmt.span,
+            Stmt::While(stmt) => &stmt.span,
+            Stmt::Break(stmt) | Stmt::Continue(stmt) => &stmt.span,
+            Stmt::Match(stmt) => &stmt.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LetStmt {
+    pub name: String,
+    pub mutable: bool,
+    pub kind: LetKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum LetKind {
+    /// `let name: Type;`
+    Declare(TypeExpr),
+    /// `let name: Type = value;`, or `let name = value;` without the type.
+    Value(Option<TypeExpr>, Expr),
+    /// `let name: Func = (params) -> Type { ... };`
+    Func(FuncExpr),
+    /// `let Name: Type = struct { ... };` or `let Name: Type = enum { ... };`
+    Type(TypeDef),
+}
+
+#[derive(Debug, Clone)]
+pub enum TypeDef {
+    Struct(Vec<FieldDecl>),
+    Enum(Vec<VariantDecl>),
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldDecl {
+    pub name: String,
+    pub i_type: TypeExpr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct VariantDecl {
+    pub name: String,
+    pub fields: Vec<FieldDecl>,
+    pub span: Span,
+}
+
+/// A type as it is written. Names of structs and enums are only looked up by `typeck`.
+#[derive(Debug, Clone)]
+pub struct TypeExpr {
+    pub kind: TypeKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum TypeKind {
+    Number,
+    String,
+    Bool,
+    Nothing,
+    Vec(Box<TypeExpr>),
+    Array(Box<TypeExpr>, usize),
+    Pointer(Box<TypeExpr>),
+    MutPointer(Box<TypeExpr>),
+    Function(Vec<TypeExpr>, Box<TypeExpr>),
+    Named(String),
+}
+
+/// `(params) -> Type { ... }`, either defining a named function or used as a lambda.
+#[derive(Debug, Clone)]
+pub struct FuncExpr {
+    pub params: Vec<Param>,
+    pub ret: TypeExpr,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub i_type: TypeExpr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct AssignStmt {
+    pub target: Place,
+    pub value: Expr,
+    pub span: Span,
+}
+
+/// What an assignment writes to.
+#[derive(Debug, Clone)]
+pub enum Place {
+    /// `name = value;`
+    Name(String),
+    /// `*name = value;`
+    Deref(String),
+    /// `name[index] = value;`
+    Index(String, Expr),
+    /// `name.field.field = value;`
+    Field(String, Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct CallExpr {
+    pub name: String,
+    pub args: Vec<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct IfStmt {
+    pub condition: Expr,
+    pub body: Vec<Stmt>,
+    pub elifs: Vec<(Expr, Vec<Stmt>)>,
+    pub i_else: Option<Vec<Stmt>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReturnStmt {
+    pub value: Option<Expr>,
+    pub span: Span,
+}
+
+/// `inline "C code";`
+#[derive(Debug, Clone)]
+pub struct InlineStmt {
+    pub code: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct ForStmt {
+    pub name: String,
+    pub iter: ForIter,
+    pub body: Vec<Stmt>,
+    pub label: Option<String>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ForIter {
+    /// `start..end`
+    Range(Expr, Expr),
+    /// A Vec or an array.
+    Elements(Expr),
+}
+
+#[derive(Debug, Clone)]
+pub struct WhileStmt {
+    pub condition: Expr,
+    pub body: Vec<Stmt>,
+    pub label: Option<String>,
+    pub span: Span,
+}
+
+/// A `break` or `continue`, with the label it names if any.
+#[derive(Debug, Clone)]
+pub struct JumpStmt {
+    pub label: Option<String>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchStmt {
+    pub value: Expr,
+    pub arms: Vec<ArmStmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct ArmStmt {
+    /// None for the `_` arm.
+    pub variant: Option<String>,
+    pub bindings: Vec<Binding>,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+/// `field` or `field: name` in a match arm, binding a field of the variant to a name.
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub field: String,
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    Num(i32),
+    Bool(bool),
+    Text(String),
+    /// A name, which may stand for a variable, a function or a type.
+    Ident(String),
+    /// `&name`
+    Ref(String),
+    /// `*name`
+    Deref(String),
+    /// `name.field.field`, which is a variant without fields when `name` is an enum.
+    Field(String, Vec<String>),
+    /// `name[index]`
+    Index(String, Box<Expr>),
+    Call(CallExpr),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// `[a, b, c]`
+    Vec(Vec<Expr>),
+    /// `[value; len]`
+    Array(Box<Expr>, usize),
+    /// `Name { field: value, ... }`, or `Name.Variant { field: value, ... }` with the variant.
+    Literal(String, Option<String>, Vec<FieldInit>),
+    Lambda(FuncExpr),
+}
+
+/// `field: value` in a struct or variant literal.
+#[derive(Debug, Clone)]
+pub struct FieldInit {
+    pub name: String,
+    pub value: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum BinaryOp {
+    Num(Operators),
+    Bool(BoolOps),
+}